@@ -0,0 +1 @@
+ALTER TABLE games ADD COLUMN version BIGINT NOT NULL DEFAULT 0;
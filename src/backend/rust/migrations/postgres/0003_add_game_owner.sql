@@ -0,0 +1,8 @@
+ALTER TABLE games ADD COLUMN owner TEXT;
+
+UPDATE games SET owner = (
+    SELECT user_id FROM user_games
+    WHERE user_games.game_id = games.id
+    ORDER BY seq
+    LIMIT 1
+);
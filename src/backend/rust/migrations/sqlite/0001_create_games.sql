@@ -0,0 +1,71 @@
+CREATE TABLE games (
+    id INTEGER PRIMARY KEY,
+    board TEXT NOT NULL,
+    mine_points TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    mines_generated BOOLEAN NOT NULL,
+    cols INTEGER NOT NULL,
+    rows INTEGER NOT NULL,
+    mine_count_target INTEGER NOT NULL,
+    seed INTEGER NOT NULL,
+    no_guess BOOLEAN NOT NULL,
+    hints_used INTEGER NOT NULL,
+    practice BOOLEAN NOT NULL,
+    started_at TEXT,
+    finished_at TEXT,
+    match_id INTEGER,
+    invite_code TEXT UNIQUE,
+    private BOOLEAN NOT NULL
+);
+
+CREATE TABLE game_moves (
+    game_id INTEGER NOT NULL REFERENCES games (id) ON DELETE CASCADE,
+    x INTEGER NOT NULL,
+    y INTEGER NOT NULL,
+    PRIMARY KEY (game_id, x, y)
+);
+
+CREATE TABLE game_flags (
+    game_id INTEGER NOT NULL REFERENCES games (id) ON DELETE CASCADE,
+    x INTEGER NOT NULL,
+    y INTEGER NOT NULL,
+    PRIMARY KEY (game_id, x, y)
+);
+
+CREATE TABLE game_actions (
+    game_id INTEGER NOT NULL REFERENCES games (id) ON DELETE CASCADE,
+    seq INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    PRIMARY KEY (game_id, seq)
+);
+
+CREATE TABLE game_participants (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    game_id INTEGER NOT NULL REFERENCES games (id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL,
+    UNIQUE (game_id, user_id)
+);
+
+CREATE TABLE user_games (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    game_id INTEGER NOT NULL,
+    UNIQUE (user_id, game_id)
+);
+
+CREATE INDEX user_games_game_id_idx ON user_games (game_id);
+
+CREATE TABLE matches (
+    id INTEGER PRIMARY KEY,
+    owner TEXT NOT NULL,
+    cols INTEGER NOT NULL,
+    rows INTEGER NOT NULL,
+    mines INTEGER NOT NULL,
+    seed INTEGER NOT NULL,
+    status TEXT NOT NULL,
+    players TEXT NOT NULL,
+    winner TEXT,
+    created_at TEXT NOT NULL,
+    started_at TEXT,
+    finished_at TEXT
+);
@@ -1,6 +1,6 @@
 use crate::auth::client::{get_callback_url, GoogleOAuthClient};
 use crate::error::{AppError, AppResult};
-use crate::model::UserInfo;
+use crate::model::{UserId, UserInfo};
 use actix_identity::Identity;
 use actix_session::Session;
 use actix_web::{web, HttpMessage, HttpRequest, HttpResponse};
@@ -120,7 +120,7 @@ pub async fn google_callback(
         .map_err(|e| AppError::Internal(format!("Failed to verify ID token: {:?}", e)))?;
 
     let user_info = UserInfo {
-        sub: claims.subject().to_string(),
+        sub: UserId::new(claims.subject().as_str()),
         name: claims
             .name()
             .and_then(|n| n.get(None).map(|v| v.to_string())),
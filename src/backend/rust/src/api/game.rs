@@ -1,9 +1,18 @@
+use crate::api::live;
 use crate::auth::IdentityExt;
-use crate::error::AppResult;
-use crate::model::{MakeMoveRequest, MinesweeperGameDto, Point};
+use crate::error::{AppError, AppResult};
+use crate::model::{
+    GameId, GameOptions, GameUpdateDto, InviteRequest, MakeMoveRequest, MinesweeperGame,
+    MinesweeperGameDto, MoveQuery, Point, ReplayQuery, UserInfo,
+};
 use crate::service::GameService;
+use crate::telemetry::metrics::MinesweeperMetrics;
 use actix_identity::Identity;
-use actix_web::{web, HttpResponse};
+use actix_web::{
+    dev::Payload, http::header::ContentType, web, FromRequest, HttpRequest, HttpResponse,
+};
+use serde::Serialize;
+use std::future::{ready, Ready};
 use std::sync::Arc;
 
 pub const SCOPE_GAME: &str = "/game";
@@ -12,75 +21,237 @@ pub const PATH_NEW: &str = "/new";
 pub const PATH_NEW_CUSTOM: &str = "/new/{cols}/{rows}/{mines}";
 pub const PATH_FLAG: &str = "/flag";
 pub const PATH_FLAG_ID: &str = "/flag/{id}";
+pub const PATH_CHORD: &str = "/chord";
+pub const PATH_CHORD_ID: &str = "/chord/{id}";
 pub const PATH_ID: &str = "/{id}";
+pub const PATH_HINT: &str = "/{id}/hint";
+pub const PATH_PROBABILITIES: &str = "/{id}/probabilities";
+pub const PATH_REPLAY: &str = "/{id}/replay";
+pub const PATH_UNDO: &str = "/{id}/undo";
+pub const PATH_INVITE: &str = "/{id}/invite";
+pub const PATH_INVITE_CODE: &str = "/{id}/invite-code";
+pub const PATH_JOIN: &str = "/join/{code}";
 
 pub async fn get_game(
-    id: Option<web::Path<i32>>,
+    id: OptionalGameId,
     identity: Option<Identity>,
     service: web::Data<Arc<dyn GameService>>,
 ) -> AppResult<HttpResponse> {
-    let id = id.map(|p| p.into_inner()).unwrap_or(0);
-
-    if id == 0 {
+    let Some(id) = id.0 else {
         return new_game_default(service, identity).await;
-    }
+    };
 
-    let game = service.get_game(id).await?;
-    Ok(HttpResponse::Ok().json(MinesweeperGameDto::from(&game)))
+    let user = identity.and_then(|id| id.user_info());
+    let game = service.get_game(id, user.clone()).await?;
+    game_response(&game, user.as_ref())
 }
 
 pub async fn new_game_default(
     service: web::Data<Arc<dyn GameService>>,
     identity: Option<Identity>,
 ) -> AppResult<HttpResponse> {
-    new_game_custom(web::Path::from((10, 10, 10)), service, identity).await
+    new_game_custom(
+        web::Path::from((10, 10, 10)),
+        web::Query(GameOptions::default()),
+        service,
+        identity,
+    )
+    .await
 }
 
 pub async fn new_game_custom(
     path: web::Path<(usize, usize, usize)>,
+    options: web::Query<GameOptions>,
     service: web::Data<Arc<dyn GameService>>,
     identity: Option<Identity>,
 ) -> AppResult<HttpResponse> {
     let (cols, rows, mines) = path.into_inner();
     let user = identity.and_then(|id| id.user_info());
 
-    let game = service.create_game(cols, rows, mines, user).await?;
-    Ok(HttpResponse::Ok().json(MinesweeperGameDto::from(&game)))
+    let game = service
+        .create_game(cols, rows, mines, options.into_inner(), user.clone())
+        .await?;
+    game_response(&game, user.as_ref())
 }
 
 pub async fn make_move(
-    path: Option<web::Path<i32>>,
+    path: OptionalGameId,
+    query: web::Query<MoveQuery>,
     req_body: web::Json<MakeMoveRequest>,
     identity: Option<Identity>,
     service: web::Data<Arc<dyn GameService>>,
 ) -> AppResult<HttpResponse> {
-    let (game_id, point) = extract_request_params(path, req_body.into_inner());
+    let (game_id, point) = extract_request_params(path, req_body.into_inner())?;
     let user = identity.and_then(|id| id.user_info());
-    let game = service.make_move(game_id, point, user).await?;
-    Ok(HttpResponse::Ok().json(MinesweeperGameDto::from(&game)))
+    let outcome = service.make_move(game_id, point, user.clone()).await?;
+    move_response(outcome, &query, user.as_ref())
 }
 
 pub async fn toggle_flag(
-    path: Option<web::Path<i32>>,
+    path: OptionalGameId,
+    query: web::Query<MoveQuery>,
+    req_body: web::Json<MakeMoveRequest>,
+    identity: Option<Identity>,
+    service: web::Data<Arc<dyn GameService>>,
+) -> AppResult<HttpResponse> {
+    let (game_id, point) = extract_request_params(path, req_body.into_inner())?;
+    let user = identity.and_then(|id| id.user_info());
+    let outcome = service.toggle_flag(game_id, point, user.clone()).await?;
+    move_response(outcome, &query, user.as_ref())
+}
+
+pub async fn invite_player(
+    path: web::Path<GameId>,
+    req_body: web::Json<InviteRequest>,
+    identity: Identity,
+    service: web::Data<Arc<dyn GameService>>,
+) -> AppResult<HttpResponse> {
+    let user = identity.user_info().ok_or(AppError::Unauthorized)?;
+    let game = service
+        .invite_player(path.into_inner(), &req_body.sub, user.clone())
+        .await?;
+    game_response(&game, Some(&user))
+}
+
+pub async fn create_invite_code(
+    path: web::Path<GameId>,
+    identity: Identity,
+    service: web::Data<Arc<dyn GameService>>,
+) -> AppResult<HttpResponse> {
+    let user = identity.user_info().ok_or(AppError::Unauthorized)?;
+    let invite = service.create_invite_code(path.into_inner(), user).await?;
+    Ok(HttpResponse::Ok().json(invite))
+}
+
+pub async fn join_game(
+    path: web::Path<String>,
+    identity: Identity,
+    service: web::Data<Arc<dyn GameService>>,
+) -> AppResult<HttpResponse> {
+    let user = identity.user_info().ok_or(AppError::Unauthorized)?;
+    let game = service.join_game(&path.into_inner(), user.clone()).await?;
+    game_response(&game, Some(&user))
+}
+
+pub async fn undo(
+    path: web::Path<GameId>,
+    identity: Option<Identity>,
+    service: web::Data<Arc<dyn GameService>>,
+) -> AppResult<HttpResponse> {
+    let user = identity.and_then(|id| id.user_info());
+    let game = service.undo(path.into_inner(), user.clone()).await?;
+    game_response(&game, user.as_ref())
+}
+
+pub async fn get_hint(
+    path: web::Path<GameId>,
+    identity: Option<Identity>,
+    service: web::Data<Arc<dyn GameService>>,
+) -> AppResult<HttpResponse> {
+    let user = identity.and_then(|id| id.user_info());
+    let hint = service.get_hint(path.into_inner(), user).await?;
+    Ok(HttpResponse::Ok().json(hint))
+}
+
+pub async fn chord(
+    path: OptionalGameId,
+    query: web::Query<MoveQuery>,
     req_body: web::Json<MakeMoveRequest>,
     identity: Option<Identity>,
     service: web::Data<Arc<dyn GameService>>,
 ) -> AppResult<HttpResponse> {
-    let (game_id, point) = extract_request_params(path, req_body.into_inner());
+    let (game_id, point) = extract_request_params(path, req_body.into_inner())?;
+    let user = identity.and_then(|id| id.user_info());
+    let outcome = service.chord(game_id, point, user.clone()).await?;
+    move_response(outcome, &query, user.as_ref())
+}
+
+pub async fn get_probabilities(
+    path: web::Path<GameId>,
+    identity: Option<Identity>,
+    service: web::Data<Arc<dyn GameService>>,
+) -> AppResult<HttpResponse> {
+    let user = identity.and_then(|id| id.user_info());
+    let probabilities = service.get_probabilities(path.into_inner(), user).await?;
+    Ok(HttpResponse::Ok().json(probabilities))
+}
+
+pub async fn get_replay(
+    path: web::Path<GameId>,
+    query: web::Query<ReplayQuery>,
+    identity: Option<Identity>,
+    service: web::Data<Arc<dyn GameService>>,
+) -> AppResult<HttpResponse> {
     let user = identity.and_then(|id| id.user_info());
-    let game = service.toggle_flag(game_id, point, user).await?;
-    Ok(HttpResponse::Ok().json(MinesweeperGameDto::from(&game)))
+    let replay = service
+        .get_replay(path.into_inner(), query.step, user)
+        .await?;
+    Ok(HttpResponse::Ok().json(replay))
+}
+
+fn move_response(
+    (game, update): (MinesweeperGame, GameUpdateDto),
+    query: &MoveQuery,
+    viewer: Option<&UserInfo>,
+) -> AppResult<HttpResponse> {
+    if query.diff {
+        json_response("update", &game, &update)
+    } else {
+        game_response(&game, viewer)
+    }
+}
+
+fn game_response(game: &MinesweeperGame, viewer: Option<&UserInfo>) -> AppResult<HttpResponse> {
+    let dto = MinesweeperGameDto::for_viewer(game, viewer.map(|u| &u.sub));
+    json_response("game", game, &dto)
+}
+
+/// Serializes the body up front so its size can be recorded against the board it
+/// describes.
+fn json_response<T: Serialize>(
+    dto: &'static str,
+    game: &MinesweeperGame,
+    body: &T,
+) -> AppResult<HttpResponse> {
+    let body = serde_json::to_vec(body)?;
+    MinesweeperMetrics::record_dto_size(dto, game, body.len());
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(body))
 }
 
 fn extract_request_params(
-    path: Option<web::Path<i32>>,
+    path: OptionalGameId,
     req: MakeMoveRequest,
-) -> (i32, Point) {
+) -> AppResult<(GameId, Point)> {
     let game_id = path
-        .map(|p| p.into_inner())
+        .0
         .or(req.game_id)
-        .unwrap_or(0);
-    (game_id, Point { x: req.x, y: req.y })
+        .ok_or_else(|| AppError::BadRequest("Game ID is required".to_string()))?;
+    Ok((game_id, Point { x: req.x, y: req.y }))
+}
+
+/// The `{id}` segment of routes that also work without one. Unlike an optional
+/// `web::Path`, an id that is present but invalid is rejected rather than ignored.
+pub struct OptionalGameId(Option<GameId>);
+
+impl FromRequest for OptionalGameId {
+    type Error = AppError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let id = req
+            .match_info()
+            .get("id")
+            .map(|raw| {
+                raw.parse::<i32>()
+                    .map_err(|e| e.to_string())
+                    .and_then(GameId::try_from)
+                    .map_err(AppError::BadRequest)
+            })
+            .transpose();
+        ready(id.map(OptionalGameId))
+    }
 }
 
 pub fn config(cfg: &mut web::ServiceConfig) {
@@ -90,8 +261,20 @@ pub fn config(cfg: &mut web::ServiceConfig) {
             .route(PATH_NEW_CUSTOM, web::get().to(new_game_custom))
             .route(PATH_FLAG, web::post().to(toggle_flag))
             .route(PATH_FLAG_ID, web::post().to(toggle_flag))
+            .route(PATH_CHORD, web::post().to(chord))
+            .route(PATH_CHORD_ID, web::post().to(chord))
+            .route(PATH_JOIN, web::post().to(join_game))
             .route("", web::get().to(get_game))
             .route(PATH_ID, web::get().to(get_game))
+            .route(PATH_HINT, web::get().to(get_hint))
+            .route(PATH_PROBABILITIES, web::get().to(get_probabilities))
+            .route(PATH_REPLAY, web::get().to(get_replay))
+            .route(PATH_UNDO, web::post().to(undo))
+            .route(PATH_INVITE, web::post().to(invite_player))
+            .route(PATH_INVITE_CODE, web::post().to(create_invite_code))
+            .route(live::PATH_WS, web::get().to(live::game_ws))
+            .route(live::PATH_EVENTS, web::get().to(live::game_events))
+            .route(live::PATH_VISIBILITY, web::post().to(live::set_visibility))
             .route("", web::post().to(make_move))
             .route(PATH_ID, web::post().to(make_move)),
     );
@@ -0,0 +1,72 @@
+use crate::auth::GoogleOAuthClient;
+use crate::model::{
+    DependencyHealth, HealthDto, HealthState, LivenessDto, ReadinessDto, RepositoryStatus,
+};
+use crate::repository::MinesweeperRepository;
+use actix_web::{web, HttpResponse};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub const SCOPE_HEALTH: &str = "/health";
+
+pub const PATH_LIVE: &str = "/live";
+pub const PATH_READY: &str = "/ready";
+
+/// A repository that has not answered by now is reported down rather than left to stall
+/// the probe.
+const PING_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Reports whether the server is running on its configured repository or on the
+/// in-memory fallback, which loses every game on restart.
+pub async fn health(repository: web::Data<RepositoryStatus>) -> HttpResponse {
+    HttpResponse::Ok().json(HealthDto::new(&repository))
+}
+
+/// Succeeds whenever the server can answer at all; dependencies are left to readiness so
+/// an outage does not get the pod restarted.
+pub async fn live() -> HttpResponse {
+    HttpResponse::Ok().json(LivenessDto {
+        status: HealthState::Ok,
+    })
+}
+
+pub async fn ready(
+    repo: web::Data<Arc<dyn MinesweeperRepository>>,
+    repository: web::Data<RepositoryStatus>,
+    google_client: Option<web::Data<GoogleOAuthClient>>,
+) -> HttpResponse {
+    let started = Instant::now();
+    let ping = tokio::time::timeout(PING_TIMEOUT, repo.ping()).await;
+    let latency_ms = Some(started.elapsed().as_secs_f64() * 1000.0);
+
+    let repository = match ping {
+        Ok(Ok(())) => match &repository.error {
+            Some(error) if repository.degraded => {
+                DependencyHealth::degraded(latency_ms, error.clone())
+            }
+            _ => DependencyHealth::ok(latency_ms),
+        },
+        Ok(Err(e)) => DependencyHealth::down(latency_ms, e.to_string()),
+        Err(_) => DependencyHealth::down(latency_ms, "Timed out".to_string()),
+    };
+    let oauth = match google_client {
+        Some(_) => DependencyHealth::ok(None),
+        None => DependencyHealth::degraded(None, "OAuth client not configured".to_string()),
+    };
+
+    let readiness = ReadinessDto::new(repository, oauth);
+    if readiness.is_ready() {
+        HttpResponse::Ok().json(readiness)
+    } else {
+        HttpResponse::ServiceUnavailable().json(readiness)
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope(SCOPE_HEALTH)
+            .route("", web::get().to(health))
+            .route(PATH_LIVE, web::get().to(live))
+            .route(PATH_READY, web::get().to(ready)),
+    );
+}
@@ -0,0 +1,203 @@
+use crate::auth::IdentityExt;
+use crate::error::{AppError, AppResult};
+use crate::events::sse_stream;
+use crate::model::{
+    GameId, GameUpdateDto, LiveCommand, LiveMessage, MinesweeperGameDto, Point, UserInfo,
+    VisibilityRequest,
+};
+use crate::service::GameService;
+use actix_identity::Identity;
+use actix_web::{http::header, web, HttpRequest, HttpResponse};
+use actix_ws::{Message, MessageStream, Session};
+use futures_util::StreamExt;
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+pub const PATH_WS: &str = "/{id}/ws";
+pub const PATH_EVENTS: &str = "/{id}/events";
+pub const PATH_VISIBILITY: &str = "/{id}/visibility";
+
+/// Upgrades to a WebSocket that accepts move commands and pushes every change to the
+/// game. Only players with write access may connect; each connection starts with a full
+/// snapshot so reconnecting clients never need to replay missed updates.
+pub async fn game_ws(
+    req: HttpRequest,
+    body: web::Payload,
+    path: web::Path<GameId>,
+    identity: Option<Identity>,
+    service: web::Data<Arc<dyn GameService>>,
+) -> AppResult<HttpResponse> {
+    let id = path.into_inner();
+    let user = identity.and_then(|id| id.user_info());
+    let (game, updates) = service.subscribe_game(id, user.clone()).await?;
+
+    let (response, session, messages) =
+        actix_ws::handle(&req, body).map_err(|e| AppError::BadRequest(e.to_string()))?;
+
+    let snapshot = LiveMessage::Snapshot {
+        game: MinesweeperGameDto::for_viewer(&game, user.as_ref().map(|u| &u.sub)),
+    };
+    actix_web::rt::spawn(run_session(
+        LiveSession {
+            id,
+            user,
+            service: service.get_ref().clone(),
+            session,
+        },
+        snapshot,
+        messages,
+        updates,
+    ));
+
+    Ok(response)
+}
+
+struct LiveSession {
+    id: GameId,
+    user: Option<UserInfo>,
+    service: Arc<dyn GameService>,
+    session: Session,
+}
+
+impl LiveSession {
+    async fn send(&mut self, message: &LiveMessage) -> bool {
+        match serde_json::to_string(message) {
+            Ok(text) => self.session.text(text).await.is_ok(),
+            Err(e) => {
+                tracing::error!("Failed to serialize live message: {}", e);
+                true
+            }
+        }
+    }
+
+    /// Applies a client command; the resulting change reaches this client through the
+    /// broadcast like any other, so only failures are answered directly.
+    async fn handle_command(&mut self, text: &str) -> bool {
+        let result = match serde_json::from_str::<LiveCommand>(text) {
+            Ok(LiveCommand::Move { x, y }) => self
+                .service
+                .make_move(self.id, Point { x, y }, self.user.clone())
+                .await
+                .map(|_| ()),
+            Ok(LiveCommand::Flag { x, y }) => self
+                .service
+                .toggle_flag(self.id, Point { x, y }, self.user.clone())
+                .await
+                .map(|_| ()),
+            Ok(LiveCommand::Chord { x, y }) => self
+                .service
+                .chord(self.id, Point { x, y }, self.user.clone())
+                .await
+                .map(|_| ()),
+            Err(e) => Err(AppError::BadRequest(e.to_string())),
+        };
+
+        match result {
+            Ok(()) => true,
+            Err(e) => {
+                self.send(&LiveMessage::Error {
+                    message: e.to_string(),
+                })
+                .await
+            }
+        }
+    }
+
+    async fn resync(&mut self) -> bool {
+        match self.service.get_game(self.id, self.user.clone()).await {
+            Ok(game) => {
+                let viewer = self.user.as_ref().map(|u| &u.sub);
+                self.send(&LiveMessage::Snapshot {
+                    game: MinesweeperGameDto::for_viewer(&game, viewer),
+                })
+                .await
+            }
+            Err(e) => {
+                self.send(&LiveMessage::Error {
+                    message: e.to_string(),
+                })
+                .await
+            }
+        }
+    }
+}
+
+async fn run_session(
+    mut live: LiveSession,
+    snapshot: LiveMessage,
+    mut messages: MessageStream,
+    mut updates: broadcast::Receiver<GameUpdateDto>,
+) {
+    if !live.send(&snapshot).await {
+        return;
+    }
+
+    loop {
+        let open = tokio::select! {
+            message = messages.next() => match message {
+                Some(Ok(Message::Text(text))) => live.handle_command(&text).await,
+                Some(Ok(Message::Ping(bytes))) => live.session.pong(&bytes).await.is_ok(),
+                Some(Ok(Message::Close(reason))) => {
+                    let _ = live.session.close(reason).await;
+                    return;
+                }
+                Some(Ok(_)) => true,
+                Some(Err(_)) | None => false,
+            },
+            update = updates.recv() => match update {
+                Ok(update) => live.send(&LiveMessage::Update(update)).await,
+                Err(RecvError::Lagged(_)) => live.resync().await,
+                Err(RecvError::Closed) => false,
+            },
+        };
+
+        if !open {
+            break;
+        }
+    }
+
+    let _ = live.session.close(None).await;
+}
+
+/// Read-only event stream for spectators: the currently visible cells first, then every
+/// change as it happens.
+pub async fn game_events(
+    path: web::Path<GameId>,
+    identity: Option<Identity>,
+    service: web::Data<Arc<dyn GameService>>,
+) -> AppResult<HttpResponse> {
+    let id = path.into_inner();
+    let user = identity.and_then(|id| id.user_info());
+    let (snapshot, updates) = service.spectate_game(id, user.clone()).await?;
+
+    // Updates are cell diffs, so a spectator that missed some needs the whole board again
+    let service = service.get_ref().clone();
+    let resync = move || {
+        let service = service.clone();
+        let user = user.clone();
+        async move {
+            service
+                .get_game(id, user)
+                .await
+                .ok()
+                .map(|game| GameUpdateDto::snapshot(&game))
+        }
+    };
+    Ok(HttpResponse::Ok()
+        .insert_header((header::CONTENT_TYPE, "text/event-stream"))
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .streaming(sse_stream(snapshot, updates, resync)))
+}
+
+pub async fn set_visibility(
+    path: web::Path<GameId>,
+    req_body: web::Json<VisibilityRequest>,
+    identity: Identity,
+    service: web::Data<Arc<dyn GameService>>,
+) -> AppResult<HttpResponse> {
+    let user = identity.user_info().ok_or(AppError::Unauthorized)?;
+    let game = service
+        .set_private(path.into_inner(), req_body.private, user.clone())
+        .await?;
+    Ok(HttpResponse::Ok().json(MinesweeperGameDto::for_viewer(&game, Some(&user.sub))))
+}
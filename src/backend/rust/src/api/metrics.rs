@@ -0,0 +1,20 @@
+use crate::error::{AppError, AppResult};
+use crate::telemetry::prometheus::PrometheusExporter;
+use actix_web::{web, HttpResponse};
+
+pub const PATH_METRICS: &str = "/metrics";
+
+const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Serves metrics for scraping when the Prometheus exporter is selected.
+pub async fn metrics() -> AppResult<HttpResponse> {
+    let exporter = PrometheusExporter::registered()
+        .ok_or_else(|| AppError::NotFound("Prometheus exporter is not enabled".to_string()))?;
+    Ok(HttpResponse::Ok()
+        .content_type(PROMETHEUS_CONTENT_TYPE)
+        .body(exporter.render()?))
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.route(PATH_METRICS, web::get().to(metrics));
+}
@@ -1,15 +1,32 @@
 pub mod auth;
 pub mod game;
+pub mod health;
+pub mod live;
+pub mod metrics;
 pub mod user;
+pub mod versus;
 
 pub use auth::config as config_auth;
 pub use game::config as config_game;
+pub use health::config as config_health;
+pub use metrics::config as config_metrics;
 pub use user::config as config_user;
+pub use versus::config as config_versus;
 
 pub use auth::SCOPE_ACCOUNT;
 pub use game::SCOPE_GAME;
+pub use health::{PATH_LIVE, PATH_READY, SCOPE_HEALTH};
 pub use user::SCOPE_USER;
+pub use versus::SCOPE_MATCH;
 
 pub use auth::{PATH_CALLBACK, PATH_LOGIN, PATH_LOGOUT, PATH_STATUS};
-pub use game::{PATH_FLAG_ID, PATH_ID, PATH_NEW, PATH_NEW_CUSTOM};
+pub use game::{
+    PATH_CHORD_ID, PATH_FLAG_ID, PATH_HINT, PATH_ID, PATH_INVITE, PATH_INVITE_CODE, PATH_JOIN,
+    PATH_NEW, PATH_NEW_CUSTOM, PATH_PROBABILITIES, PATH_REPLAY, PATH_UNDO,
+};
+pub use live::{PATH_EVENTS, PATH_VISIBILITY, PATH_WS};
+pub use metrics::PATH_METRICS;
 pub use user::{PATH_GAMES, PATH_STATS};
+pub use versus::{
+    PATH_MATCH_EVENTS, PATH_MATCH_ID, PATH_MATCH_JOIN, PATH_MATCH_NEW, PATH_MATCH_START,
+};
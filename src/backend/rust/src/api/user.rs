@@ -16,10 +16,10 @@ pub async fn user_games(
     identity: Identity,
 ) -> AppResult<HttpResponse> {
     let user = identity.user_info().ok_or(AppError::Unauthorized)?;
-    let games = service.get_user_games(user).await?;
+    let games = service.get_user_games(user.clone()).await?;
     let dtos: Vec<_> = games
-        .into_iter()
-        .map(|g| MinesweeperGameDto::from(&g))
+        .iter()
+        .map(|g| MinesweeperGameDto::for_viewer(g, Some(&user.sub)))
         .collect();
     Ok(HttpResponse::Ok().json(dtos))
 }
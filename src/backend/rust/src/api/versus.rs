@@ -0,0 +1,89 @@
+use crate::auth::IdentityExt;
+use crate::error::{AppError, AppResult};
+use crate::events::sse_stream;
+use crate::model::MatchId;
+use crate::service::MatchService;
+use actix_identity::Identity;
+use actix_web::{http::header, web, HttpResponse};
+use std::sync::Arc;
+
+pub const SCOPE_MATCH: &str = "/match";
+
+pub const PATH_MATCH_NEW: &str = "/new/{cols}/{rows}/{mines}";
+pub const PATH_MATCH_ID: &str = "/{id}";
+pub const PATH_MATCH_JOIN: &str = "/{id}/join";
+pub const PATH_MATCH_START: &str = "/{id}/start";
+pub const PATH_MATCH_EVENTS: &str = "/{id}/events";
+
+pub async fn create_match(
+    path: web::Path<(usize, usize, usize)>,
+    identity: Identity,
+    service: web::Data<Arc<dyn MatchService>>,
+) -> AppResult<HttpResponse> {
+    let (cols, rows, mines) = path.into_inner();
+    let user = identity.user_info().ok_or(AppError::Unauthorized)?;
+    let versus = service.create_match(cols, rows, mines, user).await?;
+    Ok(HttpResponse::Ok().json(versus))
+}
+
+pub async fn join_match(
+    path: web::Path<MatchId>,
+    identity: Identity,
+    service: web::Data<Arc<dyn MatchService>>,
+) -> AppResult<HttpResponse> {
+    let user = identity.user_info().ok_or(AppError::Unauthorized)?;
+    let versus = service.join_match(path.into_inner(), user).await?;
+    Ok(HttpResponse::Ok().json(versus))
+}
+
+pub async fn start_match(
+    path: web::Path<MatchId>,
+    identity: Identity,
+    service: web::Data<Arc<dyn MatchService>>,
+) -> AppResult<HttpResponse> {
+    let user = identity.user_info().ok_or(AppError::Unauthorized)?;
+    let versus = service.start_match(path.into_inner(), user).await?;
+    Ok(HttpResponse::Ok().json(versus))
+}
+
+pub async fn get_match(
+    path: web::Path<MatchId>,
+    identity: Identity,
+    service: web::Data<Arc<dyn MatchService>>,
+) -> AppResult<HttpResponse> {
+    let user = identity.user_info().ok_or(AppError::Unauthorized)?;
+    let versus = service.get_match(path.into_inner(), user).await?;
+    Ok(HttpResponse::Ok().json(versus))
+}
+
+pub async fn match_events(
+    path: web::Path<MatchId>,
+    identity: Identity,
+    service: web::Data<Arc<dyn MatchService>>,
+) -> AppResult<HttpResponse> {
+    let id = path.into_inner();
+    let user = identity.user_info().ok_or(AppError::Unauthorized)?;
+    let (current, updates) = service.subscribe_match(id, user.clone()).await?;
+
+    let service = service.get_ref().clone();
+    let resync = move || {
+        let service = service.clone();
+        let user = user.clone();
+        async move { service.get_match(id, user).await.ok() }
+    };
+    Ok(HttpResponse::Ok()
+        .insert_header((header::CONTENT_TYPE, "text/event-stream"))
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .streaming(sse_stream(current, updates, resync)))
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope(SCOPE_MATCH)
+            .route(PATH_MATCH_NEW, web::post().to(create_match))
+            .route(PATH_MATCH_ID, web::get().to(get_match))
+            .route(PATH_MATCH_JOIN, web::post().to(join_match))
+            .route(PATH_MATCH_START, web::post().to(start_match))
+            .route(PATH_MATCH_EVENTS, web::get().to(match_events)),
+    );
+}
@@ -0,0 +1,106 @@
+use anyhow::{anyhow, bail, Context};
+use std::str::FromStr;
+
+pub const USAGE: &str = "\
+Usage: loadgen [OPTIONS]
+
+Simulates concurrent players creating and finishing games, then prints a JSON report.
+
+Options:
+  --target <URL>        Backend to load [default: http://localhost:8080]
+  --in-process          Drive MinesweeperService directly with an in-memory repository
+  --players <N>         Concurrent simulated players [default: 10]
+  --games <N>           Games each player finishes [default: 10]
+  --strategy <NAME>     solver or random [default: solver]
+  --cols <N>            Board columns [default: 16]
+  --rows <N>            Board rows [default: 16]
+  --mines <N>           Mines per board [default: 40]
+  -h, --help            Print this help";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Flags and opens whatever the solver can deduce, guessing only when it is stuck.
+    Solver,
+    /// Opens random hidden cells until the game ends.
+    Random,
+}
+
+impl FromStr for Strategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "solver" => Ok(Strategy::Solver),
+            "random" => Ok(Strategy::Random),
+            other => Err(anyhow!("Unknown strategy '{}'", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Args {
+    pub target: String,
+    pub in_process: bool,
+    pub players: usize,
+    pub games: usize,
+    pub strategy: Strategy,
+    pub cols: usize,
+    pub rows: usize,
+    pub mines: usize,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Args {
+            target: "http://localhost:8080".to_string(),
+            in_process: false,
+            players: 10,
+            games: 10,
+            strategy: Strategy::Solver,
+            cols: 16,
+            rows: 16,
+            mines: 40,
+        }
+    }
+}
+
+impl Args {
+    /// Parses the command line, returning `None` when help was asked for.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> anyhow::Result<Option<Args>> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| anyhow!("Missing value for {}", arg))
+            };
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "--in-process" => parsed.in_process = true,
+                "--target" => parsed.target = value()?,
+                "--players" => parsed.players = number(&arg, value()?)?,
+                "--games" => parsed.games = number(&arg, value()?)?,
+                "--strategy" => parsed.strategy = value()?.parse()?,
+                "--cols" => parsed.cols = number(&arg, value()?)?,
+                "--rows" => parsed.rows = number(&arg, value()?)?,
+                "--mines" => parsed.mines = number(&arg, value()?)?,
+                other => bail!("Unknown argument '{}'", other),
+            }
+        }
+
+        if parsed.players == 0 || parsed.games == 0 {
+            bail!("--players and --games must be at least 1");
+        }
+        if parsed.mines >= parsed.cols * parsed.rows {
+            bail!("--mines must leave at least one safe cell");
+        }
+        Ok(Some(parsed))
+    }
+}
+
+fn number(arg: &str, value: String) -> anyhow::Result<usize> {
+    value
+        .parse()
+        .with_context(|| format!("{} expects a number, got '{}'", arg, value))
+}
@@ -0,0 +1,50 @@
+//! Load generator: simulated players create games and play them to completion against a
+//! running backend, or against `MinesweeperService` in-process, then a JSON report of
+//! throughput, latency percentiles and error rates per endpoint is printed to stdout.
+
+mod args;
+mod player;
+mod report;
+mod target;
+
+use args::{Args, USAGE};
+use player::Player;
+use report::Recorder;
+use std::sync::Arc;
+use std::time::Instant;
+use target::{HttpTarget, InProcessTarget, Target};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(Some(args)) => Arc::new(args),
+        Ok(None) => {
+            println!("{}", USAGE);
+            return Ok(());
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let (mode, target): (_, Arc<dyn Target>) = if args.in_process {
+        ("in-process", Arc::new(InProcessTarget::new()))
+    } else {
+        ("http", Arc::new(HttpTarget::new(&args.target)?))
+    };
+
+    let started = Instant::now();
+    let players: Vec<_> = (0..args.players)
+        .map(|_| tokio::spawn(Player::new(target.clone(), args.clone()).run()))
+        .collect();
+
+    let mut recorder = Recorder::default();
+    for player in players {
+        recorder.merge(player.await?);
+    }
+
+    let report = recorder.report(mode, args.players, started.elapsed());
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use crate::args::{Args, Strategy};
+use crate::report::Recorder;
+use crate::target::Target;
+use rand::seq::SliceRandom;
+use rust_backend::engine::solver;
+use rust_backend::model::{BoardState, GameStatus, MinesweeperGameDto, Point};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// One simulated player, finishing its games one after another.
+pub struct Player {
+    target: Arc<dyn Target>,
+    args: Arc<Args>,
+    recorder: Recorder,
+}
+
+impl Player {
+    pub fn new(target: Arc<dyn Target>, args: Arc<Args>) -> Self {
+        Player {
+            target,
+            args,
+            recorder: Recorder::default(),
+        }
+    }
+
+    pub async fn run(mut self) -> Recorder {
+        for _ in 0..self.args.games {
+            match self.play_game().await {
+                Ok(GameStatus::Won) => self.recorder.games.won += 1,
+                Ok(GameStatus::Lost) => self.recorder.games.lost += 1,
+                Ok(GameStatus::InProgress) => self.recorder.games.abandoned += 1,
+                Err(e) => {
+                    eprintln!("Game abandoned: {:#}", e);
+                    self.recorder.games.abandoned += 1;
+                }
+            }
+        }
+        self.recorder
+    }
+
+    async fn play_game(&mut self) -> anyhow::Result<GameStatus> {
+        let (cols, rows, mines) = (self.args.cols, self.args.rows, self.args.mines);
+        let target = self.target.clone();
+        let mut game = self
+            .timed("new_game", target.new_game(cols, rows, mines))
+            .await?;
+
+        // Every move opens or flags a cell, so this only stops a target that misbehaves
+        for _ in 0..2 * cols * rows {
+            if game.status != GameStatus::InProgress {
+                break;
+            }
+
+            let point = match self.args.strategy {
+                Strategy::Solver => {
+                    let deductions = solver::deduce(&game.board, Some(game.mine_count));
+                    let unflagged = deductions
+                        .mines
+                        .iter()
+                        .find(|p| game.board[p.x][p.y] == BoardState::Unknown);
+                    if let Some(&mine) = unflagged {
+                        game = self.timed("flag", target.flag(game.id, mine)).await?;
+                        continue;
+                    }
+                    deductions
+                        .safe
+                        .into_iter()
+                        .filter(|p| game.board[p.x][p.y] == BoardState::Unknown)
+                        .min()
+                        .or_else(|| random_hidden(&game))
+                }
+                Strategy::Random => random_hidden(&game),
+            };
+
+            match point {
+                Some(point) => game = self.timed("reveal", target.reveal(game.id, point)).await?,
+                None => break,
+            }
+        }
+
+        Ok(game.status)
+    }
+
+    async fn timed<T>(
+        &mut self,
+        endpoint: &'static str,
+        call: impl Future<Output = anyhow::Result<T>>,
+    ) -> anyhow::Result<T> {
+        let started = Instant::now();
+        let result = call.await;
+        self.recorder
+            .record(endpoint, started.elapsed(), result.is_ok());
+        result
+    }
+}
+
+fn random_hidden(game: &MinesweeperGameDto) -> Option<Point> {
+    let hidden: Vec<Point> = game
+        .board
+        .iter()
+        .enumerate()
+        .flat_map(|(x, column)| {
+            column
+                .iter()
+                .enumerate()
+                .filter(|&(_, &state)| state == BoardState::Unknown)
+                .map(move |(y, _)| Point { x, y })
+        })
+        .collect();
+    hidden.choose(&mut rand::thread_rng()).copied()
+}
@@ -0,0 +1,178 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct GameCounts {
+    pub won: usize,
+    pub lost: usize,
+    /// Games given up after a failed call.
+    pub abandoned: usize,
+}
+
+#[derive(Debug, Default)]
+struct Samples {
+    latencies: Vec<Duration>,
+    errors: usize,
+}
+
+/// Per-player measurements, merged once every player is done so recording never contends.
+#[derive(Debug, Default)]
+pub struct Recorder {
+    endpoints: BTreeMap<&'static str, Samples>,
+    pub games: GameCounts,
+}
+
+impl Recorder {
+    pub fn record(&mut self, endpoint: &'static str, elapsed: Duration, succeeded: bool) {
+        let samples = self.endpoints.entry(endpoint).or_default();
+        samples.latencies.push(elapsed);
+        if !succeeded {
+            samples.errors += 1;
+        }
+    }
+
+    pub fn merge(&mut self, other: Recorder) {
+        for (endpoint, samples) in other.endpoints {
+            let merged = self.endpoints.entry(endpoint).or_default();
+            merged.latencies.extend(samples.latencies);
+            merged.errors += samples.errors;
+        }
+        self.games.won += other.games.won;
+        self.games.lost += other.games.lost;
+        self.games.abandoned += other.games.abandoned;
+    }
+
+    pub fn report(self, mode: &'static str, players: usize, elapsed: Duration) -> Report {
+        let seconds = elapsed.as_secs_f64();
+        let endpoints = self
+            .endpoints
+            .into_iter()
+            .map(|(endpoint, samples)| (endpoint, EndpointReport::new(samples, seconds)))
+            .collect();
+
+        Report {
+            mode,
+            players,
+            elapsed_ms: millis(elapsed),
+            games: self.games,
+            endpoints,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Report {
+    pub mode: &'static str,
+    pub players: usize,
+    pub elapsed_ms: f64,
+    pub games: GameCounts,
+    pub endpoints: BTreeMap<&'static str, EndpointReport>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointReport {
+    pub requests: usize,
+    pub errors: usize,
+    pub error_rate: f64,
+    /// Requests per second over the whole run, across all players.
+    pub throughput: f64,
+    pub latency_ms: Latency,
+}
+
+impl EndpointReport {
+    fn new(mut samples: Samples, seconds: f64) -> Self {
+        samples.latencies.sort();
+        let requests = samples.latencies.len();
+
+        EndpointReport {
+            requests,
+            errors: samples.errors,
+            error_rate: samples.errors as f64 / requests.max(1) as f64,
+            throughput: if seconds > 0.0 {
+                requests as f64 / seconds
+            } else {
+                0.0
+            },
+            latency_ms: Latency::new(&samples.latencies),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Latency {
+    pub mean: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl Latency {
+    /// Expects `sorted` in ascending order.
+    fn new(sorted: &[Duration]) -> Self {
+        let total: Duration = sorted.iter().sum();
+        Latency {
+            mean: millis(total) / sorted.len().max(1) as f64,
+            p50: percentile(sorted, 50.0),
+            p90: percentile(sorted, 90.0),
+            p99: percentile(sorted, 99.0),
+            max: sorted.last().copied().map(millis).unwrap_or_default(),
+        }
+    }
+}
+
+/// Nearest-rank percentile of an ascending slice, in milliseconds.
+fn percentile(sorted: &[Duration], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    millis(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+fn millis(elapsed: Duration) -> f64 {
+    elapsed.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_use_nearest_rank() {
+        let sorted: Vec<Duration> = (1..=10).map(Duration::from_millis).collect();
+        assert_eq!(
+            Latency::new(&sorted),
+            Latency {
+                mean: 5.5,
+                p50: 5.0,
+                p90: 9.0,
+                p99: 10.0,
+                max: 10.0,
+            }
+        );
+        assert_eq!(Latency::new(&[]).p99, 0.0);
+    }
+
+    #[test]
+    fn merged_recorders_report_error_rates() {
+        let mut first = Recorder::default();
+        first.record("reveal", Duration::from_millis(2), true);
+        first.games.won = 1;
+        let mut second = Recorder::default();
+        second.record("reveal", Duration::from_millis(4), false);
+        second.games.abandoned = 1;
+        first.merge(second);
+
+        let report = first.report("http", 2, Duration::from_secs(2));
+        let reveal = &report.endpoints["reveal"];
+        assert_eq!(reveal.requests, 2);
+        assert_eq!(reveal.errors, 1);
+        assert_eq!(reveal.error_rate, 0.5);
+        assert_eq!(reveal.throughput, 1.0);
+        assert_eq!(report.games.won + report.games.abandoned, 2);
+    }
+}
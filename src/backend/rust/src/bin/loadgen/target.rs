@@ -0,0 +1,158 @@
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use rust_backend::api;
+use rust_backend::engine::MinesweeperEngine;
+use rust_backend::error::AppError;
+use rust_backend::model::{GameId, GameOptions, MakeMoveRequest, MinesweeperGameDto, Point};
+use rust_backend::repository::InMemoryGameRepository;
+use rust_backend::service::{GameService, MinesweeperService};
+use std::sync::Arc;
+
+/// The calls a simulated player makes, named after the endpoint they exercise so both
+/// modes report under the same keys.
+#[async_trait]
+pub trait Target: Send + Sync {
+    async fn new_game(
+        &self,
+        cols: usize,
+        rows: usize,
+        mines: usize,
+    ) -> anyhow::Result<MinesweeperGameDto>;
+
+    async fn reveal(&self, id: GameId, point: Point) -> anyhow::Result<MinesweeperGameDto>;
+
+    async fn flag(&self, id: GameId, point: Point) -> anyhow::Result<MinesweeperGameDto>;
+}
+
+/// Plays anonymously against a running backend over HTTP.
+pub struct HttpTarget {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl HttpTarget {
+    pub fn new(base_url: &str) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder()
+            .build()
+            .context("Failed to build HTTP client")?;
+        Ok(HttpTarget {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}{}", self.base_url, api::SCOPE_GAME, path)
+    }
+
+    async fn post_move(
+        &self,
+        path: &str,
+        id: GameId,
+        point: Point,
+    ) -> anyhow::Result<MinesweeperGameDto> {
+        let body = MakeMoveRequest {
+            x: point.x,
+            y: point.y,
+            game_id: Some(id),
+        };
+        let response = self.client.post(self.url(path)).json(&body).send().await?;
+        read_game(response).await
+    }
+}
+
+#[async_trait]
+impl Target for HttpTarget {
+    async fn new_game(
+        &self,
+        cols: usize,
+        rows: usize,
+        mines: usize,
+    ) -> anyhow::Result<MinesweeperGameDto> {
+        let path = api::PATH_NEW_CUSTOM
+            .replace("{cols}", &cols.to_string())
+            .replace("{rows}", &rows.to_string())
+            .replace("{mines}", &mines.to_string());
+        let response = self.client.get(self.url(&path)).send().await?;
+        read_game(response).await
+    }
+
+    async fn reveal(&self, id: GameId, point: Point) -> anyhow::Result<MinesweeperGameDto> {
+        self.post_move(&api::PATH_ID.replace("{id}", &id.to_string()), id, point)
+            .await
+    }
+
+    async fn flag(&self, id: GameId, point: Point) -> anyhow::Result<MinesweeperGameDto> {
+        self.post_move(
+            &api::PATH_FLAG_ID.replace("{id}", &id.to_string()),
+            id,
+            point,
+        )
+        .await
+    }
+}
+
+async fn read_game(response: reqwest::Response) -> anyhow::Result<MinesweeperGameDto> {
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(anyhow!("{} {}", status, body));
+    }
+    Ok(response.json().await?)
+}
+
+/// Calls the service directly against an in-memory repository, leaving HTTP and storage
+/// out of the measurements.
+pub struct InProcessTarget {
+    service: MinesweeperService,
+}
+
+impl InProcessTarget {
+    pub fn new() -> Self {
+        InProcessTarget {
+            service: MinesweeperService::new(
+                Arc::new(InMemoryGameRepository::new()),
+                Arc::new(MinesweeperEngine),
+            ),
+        }
+    }
+}
+
+#[async_trait]
+impl Target for InProcessTarget {
+    async fn new_game(
+        &self,
+        cols: usize,
+        rows: usize,
+        mines: usize,
+    ) -> anyhow::Result<MinesweeperGameDto> {
+        let game = self
+            .service
+            .create_game(cols, rows, mines, GameOptions::default(), None)
+            .await
+            .map_err(service_error)?;
+        Ok(MinesweeperGameDto::from(&game))
+    }
+
+    async fn reveal(&self, id: GameId, point: Point) -> anyhow::Result<MinesweeperGameDto> {
+        let (game, _) = self
+            .service
+            .make_move(id, point, None)
+            .await
+            .map_err(service_error)?;
+        Ok(MinesweeperGameDto::from(&game))
+    }
+
+    async fn flag(&self, id: GameId, point: Point) -> anyhow::Result<MinesweeperGameDto> {
+        let (game, _) = self
+            .service
+            .toggle_flag(id, point, None)
+            .await
+            .map_err(service_error)?;
+        Ok(MinesweeperGameDto::from(&game))
+    }
+}
+
+fn service_error(e: AppError) -> anyhow::Error {
+    anyhow!(e.to_string())
+}
@@ -1,10 +1,53 @@
+pub mod probability;
+pub mod replay;
+pub mod solver;
+
 use crate::model::{BoardState, MinesweeperGame, Point};
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use std::collections::{HashSet, VecDeque};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Largest board a guess-free layout is searched for; every attempt is a full solver pass.
+pub const NO_GUESS_MAX_CELLS: usize = 30 * 24;
+const NO_GUESS_MAX_ATTEMPTS: usize = 200;
+const NO_GUESS_TIME_BUDGET: Duration = Duration::from_secs(2);
+const NO_GUESS_MAX_DENSITY: f64 = 0.22;
 
 pub trait BoardEngine: Send + Sync {
     fn generate_mines(&self, game: &mut MinesweeperGame, first_click: Point);
     fn get_reveal_points(&self, game: &MinesweeperGame, p: Point) -> Vec<Point>;
+
+    /// Points revealed by chording on a revealed number whose adjacent flag count matches
+    /// it. Returns an empty list when the chord is not allowed.
+    fn get_chord_points(&self, game: &MinesweeperGame, p: Point) -> Vec<Point> {
+        if !game.mines_generated || !game.is_point_revealed(&p) {
+            return Vec::new();
+        }
+
+        let number = game.board[p.x][p.y] as i8;
+        if number <= 0 {
+            return Vec::new();
+        }
+
+        let neighbours = game.neighbours(&p);
+        let flags = neighbours
+            .iter()
+            .filter(|n| game.is_point_flagged(n))
+            .count();
+        if flags != number as usize {
+            return Vec::new();
+        }
+
+        let mut points = HashSet::new();
+        for n in neighbours {
+            if !game.is_point_flagged(&n) && !game.is_point_revealed(&n) {
+                points.extend(self.get_reveal_points(game, n));
+            }
+        }
+        points.into_iter().collect()
+    }
 }
 
 pub struct MinesweeperEngine;
@@ -15,8 +58,9 @@ impl BoardEngine for MinesweeperEngine {
             return;
         }
 
-        let mut rng = rand::thread_rng();
-        let mut mine_points = HashSet::new();
+        // Games stored before seeds existed get one on their first click
+        let seed = *game.seed.get_or_insert_with(|| rand::thread_rng().gen());
+        let mut rng = seeded_rng(seed, game.rows, first_click);
 
         let mut safe_zone = HashSet::new();
         for dx in -1..=1 {
@@ -36,6 +80,74 @@ impl BoardEngine for MinesweeperEngine {
         let max_mines = (game.cols * game.rows).saturating_sub(safe_zone.len());
         let mine_count = game.mine_count_target.min(max_mines);
 
+        if game.no_guess {
+            let density = mine_count as f64 / max_mines.max(1) as f64;
+            if game.cols * game.rows > NO_GUESS_MAX_CELLS {
+                warn!(
+                    "Board of game {} too large for a guess-free layout, using a random one",
+                    game.id
+                );
+            } else if density > NO_GUESS_MAX_DENSITY {
+                warn!(
+                    "Mine density {:.2} too high for a guess-free layout in game {}, using a random one",
+                    density, game.id
+                );
+            } else {
+                let started = Instant::now();
+                for _ in 0..NO_GUESS_MAX_ATTEMPTS {
+                    self.place_mines(game, &mut rng, &safe_zone, mine_count);
+                    if solver::solves_without_guessing(self, game, first_click) {
+                        return;
+                    }
+                    if started.elapsed() > NO_GUESS_TIME_BUDGET {
+                        break;
+                    }
+                }
+                warn!(
+                    "No guess-free layout found for game {} in time, keeping the last one",
+                    game.id
+                );
+                // The layout may need guessing, so the game must not claim otherwise
+                game.no_guess = false;
+                return;
+            }
+            game.no_guess = false;
+        }
+
+        self.place_mines(game, &mut rng, &safe_zone, mine_count);
+    }
+
+    fn get_reveal_points(&self, game: &MinesweeperGame, p: Point) -> Vec<Point> {
+        if !game.mines_generated {
+            return vec![p];
+        }
+
+        match game.board[p.x][p.y] {
+            BoardState::Zero => self.get_zero_moves(game, p),
+            BoardState::Mine => game.mine_points.iter().cloned().collect(),
+            _ => vec![p],
+        }
+    }
+}
+
+/// Mine placement is a pure function of the game seed and the first click, so any
+/// board can be regenerated from those two values.
+pub fn seeded_rng(seed: u32, rows: usize, first_click: Point) -> ChaCha8Rng {
+    let click = (first_click.x * rows + first_click.y) as u64;
+    ChaCha8Rng::seed_from_u64((u64::from(seed) << 32) | (click & 0xFFFF_FFFF))
+}
+
+impl MinesweeperEngine {
+    fn place_mines(
+        &self,
+        game: &mut MinesweeperGame,
+        rng: &mut ChaCha8Rng,
+        safe_zone: &HashSet<Point>,
+        mine_count: usize,
+    ) {
+        game.board = vec![vec![BoardState::Zero; game.rows]; game.cols];
+        let mut mine_points = HashSet::new();
+
         while mine_points.len() < mine_count {
             let x = rng.gen_range(0..game.cols);
             let y = rng.gen_range(0..game.rows);
@@ -71,20 +183,6 @@ impl BoardEngine for MinesweeperEngine {
         game.mines_generated = true;
     }
 
-    fn get_reveal_points(&self, game: &MinesweeperGame, p: Point) -> Vec<Point> {
-        if !game.mines_generated {
-            return vec![p];
-        }
-
-        match game.board[p.x][p.y] {
-            BoardState::Zero => self.get_zero_moves(game, p),
-            BoardState::Mine => game.mine_points.iter().cloned().collect(),
-            _ => vec![p],
-        }
-    }
-}
-
-impl MinesweeperEngine {
     fn get_zero_moves(&self, game: &MinesweeperGame, start: Point) -> Vec<Point> {
         let mut points = Vec::new();
         let mut visited = HashSet::new();
@@ -125,3 +223,89 @@ impl MinesweeperEngine {
         points
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::GameOptions;
+
+    fn seeded_game(seed: u32) -> MinesweeperGame {
+        MinesweeperGame::new(
+            16,
+            16,
+            40,
+            &GameOptions {
+                seed: Some(seed),
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn same_seed_and_first_click_generate_same_board() {
+        let first_click = Point { x: 3, y: 7 };
+        let mut a = seeded_game(42);
+        let mut b = seeded_game(42);
+
+        MinesweeperEngine.generate_mines(&mut a, first_click);
+        MinesweeperEngine.generate_mines(&mut b, first_click);
+
+        assert_eq!(a.mine_points, b.mine_points);
+        assert_eq!(a.board, b.board);
+    }
+
+    #[test]
+    fn different_seeds_generate_different_boards() {
+        let first_click = Point { x: 3, y: 7 };
+        let mut a = seeded_game(42);
+        let mut b = seeded_game(43);
+
+        MinesweeperEngine.generate_mines(&mut a, first_click);
+        MinesweeperEngine.generate_mines(&mut b, first_click);
+
+        assert_ne!(a.mine_points, b.mine_points);
+    }
+
+    #[test]
+    fn games_stored_without_a_seed_get_one_on_first_click() {
+        let mut game = seeded_game(42);
+        game.seed = None;
+
+        MinesweeperEngine.generate_mines(&mut game, Point { x: 3, y: 7 });
+
+        let seed = game.seed.expect("first click should assign a seed");
+        let mut replayed = seeded_game(seed);
+        MinesweeperEngine.generate_mines(&mut replayed, Point { x: 3, y: 7 });
+        assert_eq!(game.mine_points, replayed.mine_points);
+    }
+
+    #[test]
+    fn no_guess_boards_can_be_cleared_by_the_solver() {
+        let first_click = Point { x: 8, y: 8 };
+        for seed in 0..5 {
+            let mut game = seeded_game(seed);
+            game.no_guess = true;
+
+            MinesweeperEngine.generate_mines(&mut game, first_click);
+
+            assert_eq!(game.mine_points.len(), 40);
+            assert!(game.no_guess);
+            assert!(solver::solves_without_guessing(
+                &MinesweeperEngine,
+                &game,
+                first_click
+            ));
+        }
+    }
+
+    #[test]
+    fn no_guess_falls_back_to_random_layout_on_dense_boards() {
+        let mut game = MinesweeperGame::new(9, 9, 40, &GameOptions::default());
+        game.no_guess = true;
+
+        MinesweeperEngine.generate_mines(&mut game, Point { x: 4, y: 4 });
+
+        assert_eq!(game.mine_points.len(), 40);
+        assert!(!game.no_guess);
+    }
+}
@@ -0,0 +1,502 @@
+use crate::engine::solver::{is_hidden, neighbours};
+use crate::model::{BoardState, Point};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+const ENUMERATION_BUDGET: usize = 2_000_000;
+
+struct Component {
+    cells: Vec<Point>,
+    constraints: Vec<(Vec<usize>, usize)>,
+}
+
+/// Solution counts for one frontier component, keyed by how many mines it holds.
+/// Counts are scaled by a per-component constant, which cancels out when normalising.
+/// Per-cell counts only exist for mine counts with at least one solution; every solution
+/// costs a full pass of the budget over the component, which bounds their total size.
+struct Distribution {
+    counts: Vec<f64>,
+    cell_counts: BTreeMap<usize, Vec<f64>>,
+}
+
+/// Exact mine probability for every hidden cell of a player-visible board, weighting each
+/// frontier configuration by the number of ways the remaining mines fit in unconstrained
+/// cells. Revealed cells map to `None`. Returns `None` when the board is too complex to
+/// enumerate or admits no consistent layout.
+pub fn mine_probabilities(
+    board: &[Vec<BoardState>],
+    mine_count: usize,
+) -> Option<Vec<Vec<Option<f64>>>> {
+    let cols = board.len();
+    let rows = board.first().map_or(0, |col| col.len());
+
+    let known_mines = board
+        .iter()
+        .flatten()
+        .filter(|s| **s == BoardState::Mine)
+        .count();
+    let remaining_mines = mine_count.checked_sub(known_mines)?;
+
+    let mut constraints = Vec::new();
+    for (x, col) in board.iter().enumerate() {
+        for (y, state) in col.iter().enumerate() {
+            let number = *state as i8;
+            if !(0..=8).contains(&number) {
+                continue;
+            }
+
+            let mut cells = Vec::new();
+            let mut mines = number as usize;
+            for n in neighbours(cols, rows, Point { x, y }) {
+                match board[n.x][n.y] {
+                    BoardState::Mine => mines = mines.checked_sub(1)?,
+                    s if is_hidden(s) => cells.push(n),
+                    _ => {}
+                }
+            }
+
+            if cells.len() < mines {
+                return None;
+            }
+            if !cells.is_empty() {
+                constraints.push((cells, mines));
+            }
+        }
+    }
+
+    let components = split_components(&constraints);
+    let frontier: HashSet<Point> = components
+        .iter()
+        .flat_map(|c| c.cells.iter().copied())
+        .collect();
+    let interior: Vec<Point> = (0..cols)
+        .flat_map(|x| (0..rows).map(move |y| Point { x, y }))
+        .filter(|p| is_hidden(board[p.x][p.y]) && !frontier.contains(p))
+        .collect();
+
+    let mut budget = ENUMERATION_BUDGET;
+    let distributions = components
+        .iter()
+        .map(|c| enumerate(c, &mut budget))
+        .collect::<Option<Vec<_>>>()?;
+
+    // Weight of a total frontier mine count m: ways to place the rest in the interior
+    let frontier_max: usize = distributions.iter().map(|d| d.counts.len() - 1).sum();
+    let ln_factorial = ln_factorials(interior.len());
+    let ln_weights: Vec<Option<f64>> = (0..=frontier_max)
+        .map(|m| {
+            let rest = remaining_mines.checked_sub(m)?;
+            (rest <= interior.len()).then(|| {
+                ln_factorial[interior.len()]
+                    - ln_factorial[rest]
+                    - ln_factorial[interior.len() - rest]
+            })
+        })
+        .collect();
+    let ln_max = ln_weights
+        .iter()
+        .flatten()
+        .copied()
+        .fold(f64::NEG_INFINITY, f64::max);
+    if ln_max == f64::NEG_INFINITY {
+        return None;
+    }
+    let weights: Vec<f64> = ln_weights
+        .iter()
+        .map(|w| w.map_or(0.0, |w| (w - ln_max).exp()))
+        .collect();
+
+    // prefix[i] is the convolution of components before i, suffix[i] of those from i on
+    let mut prefix = vec![vec![1.0]];
+    for d in &distributions {
+        let next = convolve(prefix.last().unwrap(), &d.counts);
+        prefix.push(next);
+    }
+    let mut suffix = vec![vec![1.0]];
+    for d in distributions.iter().rev() {
+        let next = convolve(suffix.last().unwrap(), &d.counts);
+        suffix.push(next);
+    }
+    suffix.reverse();
+
+    let all = &prefix[distributions.len()];
+    let total: f64 = all.iter().zip(&weights).map(|(c, w)| c * w).sum();
+    if total <= 0.0 {
+        return None;
+    }
+
+    let mut result = vec![vec![None; rows]; cols];
+
+    for (i, (component, d)) in components.iter().zip(&distributions).enumerate() {
+        let others = convolve(&prefix[i], &suffix[i + 1]);
+        for (&k, cell_counts) in &d.cell_counts {
+            let weight: f64 = others
+                .iter()
+                .enumerate()
+                .map(|(j, c)| c * weights.get(j + k).copied().unwrap_or(0.0))
+                .sum();
+            for (c, p) in component.cells.iter().enumerate() {
+                let entry = result[p.x][p.y].get_or_insert(0.0);
+                *entry += cell_counts[c] * weight / total;
+            }
+        }
+    }
+
+    if !interior.is_empty() {
+        let expected_interior_mines: f64 = all
+            .iter()
+            .zip(&weights)
+            .enumerate()
+            .map(|(m, (c, w))| c * w * remaining_mines.saturating_sub(m) as f64)
+            .sum::<f64>()
+            / total;
+        let probability = expected_interior_mines / interior.len() as f64;
+        for p in &interior {
+            result[p.x][p.y] = Some(probability);
+        }
+    }
+
+    Some(result)
+}
+
+fn split_components(constraints: &[(Vec<Point>, usize)]) -> Vec<Component> {
+    let mut by_cell: HashMap<Point, Vec<usize>> = HashMap::new();
+    for (i, (cells, _)) in constraints.iter().enumerate() {
+        for p in cells {
+            by_cell.entry(*p).or_default().push(i);
+        }
+    }
+
+    let mut seen = vec![false; constraints.len()];
+    let mut components = Vec::new();
+
+    for start in 0..constraints.len() {
+        if seen[start] {
+            continue;
+        }
+
+        // Breadth-first order keeps related cells adjacent, which makes pruning effective
+        let mut index: HashMap<Point, usize> = HashMap::new();
+        let mut cells = Vec::new();
+        let mut members = Vec::new();
+        let mut queue = VecDeque::from([start]);
+        seen[start] = true;
+
+        while let Some(ci) = queue.pop_front() {
+            members.push(ci);
+            for p in &constraints[ci].0 {
+                if !index.contains_key(p) {
+                    index.insert(*p, cells.len());
+                    cells.push(*p);
+                }
+                for &other in &by_cell[p] {
+                    if !seen[other] {
+                        seen[other] = true;
+                        queue.push_back(other);
+                    }
+                }
+            }
+        }
+
+        let constraints = members
+            .iter()
+            .map(|&ci| {
+                let (cells, mines) = &constraints[ci];
+                (cells.iter().map(|p| index[p]).collect(), *mines)
+            })
+            .collect();
+
+        components.push(Component { cells, constraints });
+    }
+
+    components
+}
+
+fn enumerate(component: &Component, budget: &mut usize) -> Option<Distribution> {
+    let n = component.cells.len();
+    // Reaching a single solution takes one step per cell
+    if n >= *budget {
+        return None;
+    }
+    let mut cell_constraints = vec![Vec::new(); n];
+    for (ci, (cells, _)) in component.constraints.iter().enumerate() {
+        for &c in cells {
+            cell_constraints[c].push(ci);
+        }
+    }
+
+    let mut search = Search {
+        component,
+        cell_constraints,
+        assigned: vec![0; component.constraints.len()],
+        unassigned: component
+            .constraints
+            .iter()
+            .map(|(cells, _)| cells.len())
+            .collect(),
+        mines: vec![false; n],
+        distribution: Distribution {
+            counts: vec![0.0; n + 1],
+            cell_counts: BTreeMap::new(),
+        },
+        budget,
+    };
+
+    if !search.run(0) {
+        return None;
+    }
+
+    let mut distribution = search.distribution;
+    let scale = distribution.counts.iter().copied().fold(0.0, f64::max);
+    if scale > 0.0 {
+        for count in &mut distribution.counts {
+            *count /= scale;
+        }
+        for cell_counts in distribution.cell_counts.values_mut() {
+            for count in cell_counts {
+                *count /= scale;
+            }
+        }
+    }
+    Some(distribution)
+}
+
+struct Search<'a> {
+    component: &'a Component,
+    cell_constraints: Vec<Vec<usize>>,
+    assigned: Vec<usize>,
+    unassigned: Vec<usize>,
+    mines: Vec<bool>,
+    distribution: Distribution,
+    budget: &'a mut usize,
+}
+
+impl Search<'_> {
+    /// Returns false once the enumeration budget is exhausted.
+    fn run(&mut self, cell: usize) -> bool {
+        if *self.budget == 0 {
+            return false;
+        }
+        *self.budget -= 1;
+
+        if cell == self.mines.len() {
+            let k = self.mines.iter().filter(|m| **m).count();
+            self.distribution.counts[k] += 1.0;
+            let cell_counts = self
+                .distribution
+                .cell_counts
+                .entry(k)
+                .or_insert_with(|| vec![0.0; self.mines.len()]);
+            for (c, mine) in self.mines.iter().enumerate() {
+                if *mine {
+                    cell_counts[c] += 1.0;
+                }
+            }
+            return true;
+        }
+
+        for mine in [false, true] {
+            self.mines[cell] = mine;
+            let mut feasible = true;
+            for &ci in &self.cell_constraints[cell] {
+                self.unassigned[ci] -= 1;
+                if mine {
+                    self.assigned[ci] += 1;
+                }
+                let target = self.component.constraints[ci].1;
+                if self.assigned[ci] > target || self.assigned[ci] + self.unassigned[ci] < target {
+                    feasible = false;
+                }
+            }
+
+            let completed = !feasible || self.run(cell + 1);
+
+            for &ci in &self.cell_constraints[cell] {
+                self.unassigned[ci] += 1;
+                if mine {
+                    self.assigned[ci] -= 1;
+                }
+            }
+
+            if !completed {
+                return false;
+            }
+        }
+
+        self.mines[cell] = false;
+        true
+    }
+}
+
+fn convolve(a: &[f64], b: &[f64]) -> Vec<f64> {
+    let mut result = vec![0.0; a.len() + b.len() - 1];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            result[i + j] += x * y;
+        }
+    }
+    result
+}
+
+fn ln_factorials(n: usize) -> Vec<f64> {
+    let mut table = vec![0.0; n + 1];
+    for i in 1..=n {
+        table[i] = table[i - 1] + (i as f64).ln();
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn visible(
+        cols: usize,
+        rows: usize,
+        mines: &[Point],
+        revealed: &[Point],
+    ) -> Vec<Vec<BoardState>> {
+        let mines: HashSet<Point> = mines.iter().copied().collect();
+        let mut board = vec![vec![BoardState::Unknown; rows]; cols];
+        for p in revealed {
+            let count = neighbours(cols, rows, *p)
+                .filter(|n| mines.contains(n))
+                .count();
+            let mut state = BoardState::Zero;
+            for _ in 0..count {
+                state = state.increment();
+            }
+            board[p.x][p.y] = state;
+        }
+        board
+    }
+
+    fn brute_force(board: &[Vec<BoardState>], mine_count: usize) -> Vec<Vec<Option<f64>>> {
+        let cols = board.len();
+        let rows = board[0].len();
+        let hidden: Vec<Point> = (0..cols)
+            .flat_map(|x| (0..rows).map(move |y| Point { x, y }))
+            .filter(|p| is_hidden(board[p.x][p.y]))
+            .collect();
+
+        let mut totals = vec![0.0; hidden.len()];
+        let mut solutions = 0.0;
+        for mask in 0u32..(1 << hidden.len()) {
+            if mask.count_ones() as usize != mine_count {
+                continue;
+            }
+            let mines: HashSet<Point> = hidden
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| mask & (1 << i) != 0)
+                .map(|(_, p)| *p)
+                .collect();
+            let consistent = (0..cols).all(|x| {
+                (0..rows).all(|y| {
+                    let number = board[x][y] as i8;
+                    number < 0
+                        || neighbours(cols, rows, Point { x, y })
+                            .filter(|n| mines.contains(n))
+                            .count()
+                            == number as usize
+                })
+            });
+            if consistent {
+                solutions += 1.0;
+                for (i, p) in hidden.iter().enumerate() {
+                    if mines.contains(p) {
+                        totals[i] += 1.0;
+                    }
+                }
+            }
+        }
+
+        let mut result = vec![vec![None; rows]; cols];
+        for (i, p) in hidden.iter().enumerate() {
+            result[p.x][p.y] = Some(totals[i] / solutions);
+        }
+        result
+    }
+
+    fn assert_close(actual: &[Vec<Option<f64>>], expected: &[Vec<Option<f64>>]) {
+        for (a_col, e_col) in actual.iter().zip(expected) {
+            for (a, e) in a_col.iter().zip(e_col) {
+                match (a, e) {
+                    (Some(a), Some(e)) => assert!((a - e).abs() < 1e-9, "{} != {}", a, e),
+                    (None, None) => {}
+                    _ => panic!("{:?} != {:?}", a, e),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn inconsistent_mine_count_has_no_probabilities() {
+        let board = visible(3, 1, &[Point { x: 0, y: 0 }], &[Point { x: 1, y: 0 }]);
+
+        assert!(mine_probabilities(&board, 3).is_none());
+    }
+
+    #[test]
+    fn components_larger_than_the_budget_are_not_enumerated() {
+        let component = Component {
+            cells: (0..1000).map(|x| Point { x, y: 0 }).collect(),
+            constraints: vec![((0..1000).collect(), 500)],
+        };
+        let mut budget = 1000;
+
+        assert!(enumerate(&component, &mut budget).is_none());
+        assert_eq!(budget, 1000);
+    }
+
+    #[test]
+    fn unrevealed_board_is_uniform() {
+        let board = vec![vec![BoardState::Unknown; 3]; 3];
+
+        let probabilities = mine_probabilities(&board, 3).unwrap();
+
+        for p in probabilities.iter().flatten() {
+            assert!((p.unwrap() - 1.0 / 3.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn deduced_cells_are_certain() {
+        let revealed: Vec<Point> = (0..2)
+            .flat_map(|x| (0..3).map(move |y| Point { x, y }))
+            .collect();
+        let board = visible(
+            4,
+            3,
+            &[Point { x: 2, y: 0 }, Point { x: 3, y: 2 }],
+            &revealed,
+        );
+
+        let probabilities = mine_probabilities(&board, 2).unwrap();
+
+        assert_eq!(probabilities[0][0], None);
+        assert!((probabilities[2][0].unwrap() - 1.0).abs() < 1e-9);
+        assert!(probabilities[2][1].unwrap().abs() < 1e-9);
+        assert!((probabilities[3][1].unwrap() - 1.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn matches_brute_force_with_unconstrained_cells() {
+        let mines = [
+            Point { x: 0, y: 0 },
+            Point { x: 4, y: 0 },
+            Point { x: 1, y: 3 },
+            Point { x: 4, y: 3 },
+        ];
+        let revealed = [
+            Point { x: 1, y: 1 },
+            Point { x: 2, y: 2 },
+            Point { x: 0, y: 2 },
+        ];
+        let board = visible(5, 4, &mines, &revealed);
+
+        for mine_count in 3..=6 {
+            let probabilities = mine_probabilities(&board, mine_count).unwrap();
+            assert_close(&probabilities, &brute_force(&board, mine_count));
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use crate::engine::BoardEngine;
+use crate::model::{ActionKind, MinesweeperGame};
+
+/// Rebuilds the game as it stood after the first `step` actions of its log. The mine
+/// layout is taken from the stored board, so only the player-visible state is replayed.
+pub fn replay(engine: &dyn BoardEngine, game: &MinesweeperGame, step: usize) -> MinesweeperGame {
+    let mut state = game.clone();
+    state.moves.clear();
+    state.flag_points.clear();
+    state.actions.clear();
+    state.finished_at = None;
+
+    for action in game.actions.iter().take(step) {
+        match action.kind {
+            ActionKind::Reveal => {
+                let points = engine.get_reveal_points(&state, action.point);
+                state.moves.extend(points);
+            }
+            ActionKind::Chord => {
+                let points = engine.get_chord_points(&state, action.point);
+                state.moves.extend(points);
+            }
+            ActionKind::Flag => {
+                state.flag_points.insert(action.point);
+            }
+            ActionKind::Unflag => {
+                state.flag_points.remove(&action.point);
+            }
+        }
+        state.actions.push(action.clone());
+    }
+
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::MinesweeperEngine;
+    use crate::model::{BoardState, GameAction, GameOptions, Point};
+
+    fn apply(game: &mut MinesweeperGame, kind: ActionKind, point: Point) {
+        let points = match kind {
+            ActionKind::Reveal => MinesweeperEngine.get_reveal_points(game, point),
+            ActionKind::Chord => MinesweeperEngine.get_chord_points(game, point),
+            ActionKind::Flag => {
+                game.flag_points.insert(point);
+                Vec::new()
+            }
+            ActionKind::Unflag => {
+                game.flag_points.remove(&point);
+                Vec::new()
+            }
+        };
+        let action = GameAction::new(kind, point, game.unrevealed(&points));
+        game.moves.extend(points);
+        game.actions.push(action);
+    }
+
+    #[test]
+    fn replaying_the_full_log_reproduces_the_game() {
+        let mut game = MinesweeperGame::new(
+            9,
+            9,
+            10,
+            &GameOptions {
+                seed: Some(7),
+                ..Default::default()
+            },
+        );
+        let first_click = Point { x: 4, y: 4 };
+        MinesweeperEngine.generate_mines(&mut game, first_click);
+        apply(&mut game, ActionKind::Reveal, first_click);
+
+        let mine = *game.mine_points.iter().min().unwrap();
+        let safe = (0..game.cols)
+            .flat_map(|x| (0..game.rows).map(move |y| Point { x, y }))
+            .find(|p| !game.mine_points.contains(p) && !game.is_point_revealed(p))
+            .unwrap();
+        apply(&mut game, ActionKind::Flag, mine);
+        apply(&mut game, ActionKind::Flag, safe);
+        apply(&mut game, ActionKind::Unflag, safe);
+        apply(&mut game, ActionKind::Reveal, safe);
+
+        let replayed = replay(&MinesweeperEngine, &game, game.actions.len());
+        assert_eq!(replayed.visible_board(), game.visible_board());
+        assert_eq!(replayed.actions, game.actions);
+
+        let start = replay(&MinesweeperEngine, &game, 0);
+        assert!(start
+            .visible_board()
+            .iter()
+            .flatten()
+            .all(|s| *s == BoardState::Unknown));
+
+        let after_flags = replay(&MinesweeperEngine, &game, 3);
+        assert!(after_flags.is_point_flagged(&mine));
+        assert!(after_flags.is_point_flagged(&safe));
+        assert!(!after_flags.is_point_revealed(&safe));
+    }
+}
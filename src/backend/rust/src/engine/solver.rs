@@ -0,0 +1,304 @@
+use crate::engine::BoardEngine;
+use crate::model::{BoardState, MinesweeperGame, Point};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Deductions {
+    pub safe: HashSet<Point>,
+    pub mines: HashSet<Point>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Constraint {
+    cells: BTreeSet<Point>,
+    mines: usize,
+}
+
+pub(crate) fn is_hidden(state: BoardState) -> bool {
+    matches!(state, BoardState::Unknown | BoardState::Flag)
+}
+
+pub(crate) fn neighbours(cols: usize, rows: usize, p: Point) -> impl Iterator<Item = Point> {
+    (-1isize..=1)
+        .flat_map(|dx| (-1isize..=1).map(move |dy| (dx, dy)))
+        .filter(|&(dx, dy)| dx != 0 || dy != 0)
+        .filter_map(move |(dx, dy)| {
+            let nx = p.x as isize + dx;
+            let ny = p.y as isize + dy;
+            if nx >= 0 && nx < cols as isize && ny >= 0 && ny < rows as isize {
+                Some(Point {
+                    x: nx as usize,
+                    y: ny as usize,
+                })
+            } else {
+                None
+            }
+        })
+}
+
+/// Deduces which hidden cells of a player-visible board are certainly safe or certainly
+/// mined. Flags are treated as hidden cells since the player may have placed them wrongly.
+/// When `mine_count` is known it is used as an extra constraint over all hidden cells.
+pub fn deduce(board: &[Vec<BoardState>], mine_count: Option<usize>) -> Deductions {
+    let cols = board.len();
+    let rows = board.first().map_or(0, |col| col.len());
+
+    let mut known_mines = HashSet::new();
+    for (x, col) in board.iter().enumerate() {
+        for (y, state) in col.iter().enumerate() {
+            if *state == BoardState::Mine {
+                known_mines.insert(Point { x, y });
+            }
+        }
+    }
+
+    let mut deductions = Deductions::default();
+
+    loop {
+        let constraints = build_constraints(
+            board,
+            cols,
+            rows,
+            &known_mines,
+            &deductions.safe,
+            mine_count,
+        );
+
+        let mut progress = false;
+
+        for c in &constraints {
+            if c.mines == 0 {
+                progress |= mark(c.cells.iter(), false, &mut deductions, &mut known_mines);
+            } else if c.mines == c.cells.len() {
+                progress |= mark(c.cells.iter(), true, &mut deductions, &mut known_mines);
+            }
+        }
+
+        if !progress {
+            let mut by_cell: HashMap<Point, Vec<usize>> = HashMap::new();
+            for (i, c) in constraints.iter().enumerate() {
+                for p in &c.cells {
+                    by_cell.entry(*p).or_default().push(i);
+                }
+            }
+
+            for (i, a) in constraints.iter().enumerate() {
+                let Some(first) = a.cells.iter().next() else {
+                    continue;
+                };
+                for &j in &by_cell[first] {
+                    let b = &constraints[j];
+                    if i == j || b.cells.len() <= a.cells.len() || !a.cells.is_subset(&b.cells) {
+                        continue;
+                    }
+
+                    let rest = b.cells.difference(&a.cells);
+                    let rest_mines = b.mines.saturating_sub(a.mines);
+                    if rest_mines == 0 {
+                        progress |= mark(rest, false, &mut deductions, &mut known_mines);
+                    } else if rest_mines == b.cells.len() - a.cells.len() {
+                        progress |= mark(rest, true, &mut deductions, &mut known_mines);
+                    }
+                }
+            }
+        }
+
+        if !progress {
+            return deductions;
+        }
+    }
+}
+
+/// Picks a provably safe hidden cell, preferring the lowest coordinates so repeated
+/// requests for the same position return the same hint.
+pub fn find_safe_cell(board: &[Vec<BoardState>], mine_count: usize) -> Option<Point> {
+    deduce(board, Some(mine_count)).safe.into_iter().min()
+}
+
+fn mark<'a>(
+    cells: impl Iterator<Item = &'a Point>,
+    mine: bool,
+    deductions: &mut Deductions,
+    known_mines: &mut HashSet<Point>,
+) -> bool {
+    let mut progress = false;
+    for p in cells {
+        progress |= if mine {
+            known_mines.insert(*p);
+            deductions.mines.insert(*p)
+        } else {
+            deductions.safe.insert(*p)
+        };
+    }
+    progress
+}
+
+fn build_constraints(
+    board: &[Vec<BoardState>],
+    cols: usize,
+    rows: usize,
+    known_mines: &HashSet<Point>,
+    known_safe: &HashSet<Point>,
+    mine_count: Option<usize>,
+) -> Vec<Constraint> {
+    let mut constraints = Vec::new();
+    let unresolved = |p: &Point| {
+        is_hidden(board[p.x][p.y]) && !known_mines.contains(p) && !known_safe.contains(p)
+    };
+
+    for (x, col) in board.iter().enumerate() {
+        for (y, state) in col.iter().enumerate() {
+            let number = *state as i8;
+            if !(0..=8).contains(&number) {
+                continue;
+            }
+
+            let p = Point { x, y };
+            let mut cells = BTreeSet::new();
+            let mut mines = number as usize;
+            for n in neighbours(cols, rows, p) {
+                if known_mines.contains(&n) {
+                    mines = mines.saturating_sub(1);
+                } else if unresolved(&n) {
+                    cells.insert(n);
+                }
+            }
+
+            if !cells.is_empty() {
+                constraints.push(Constraint { cells, mines });
+            }
+        }
+    }
+
+    if let Some(total) = mine_count {
+        let cells: BTreeSet<Point> = (0..cols)
+            .flat_map(|x| (0..rows).map(move |y| Point { x, y }))
+            .filter(|p| unresolved(p))
+            .collect();
+        if !cells.is_empty() {
+            constraints.push(Constraint {
+                cells,
+                mines: total.saturating_sub(known_mines.len()),
+            });
+        }
+    }
+
+    constraints
+}
+
+/// Plays the game from `first_click` using only deductions available to a player and
+/// reports whether every safe cell can be revealed without guessing.
+pub fn solves_without_guessing(
+    engine: &dyn BoardEngine,
+    game: &MinesweeperGame,
+    first_click: Point,
+) -> bool {
+    let target = game.cols * game.rows - game.mine_points.len();
+    let mut visible = vec![vec![BoardState::Unknown; game.rows]; game.cols];
+    let mut revealed = HashSet::new();
+
+    let mut reveal = |p: Point, visible: &mut Vec<Vec<BoardState>>| {
+        let mut progress = false;
+        for q in engine.get_reveal_points(game, p) {
+            if revealed.insert(q) {
+                visible[q.x][q.y] = game.board[q.x][q.y];
+                progress = true;
+            }
+        }
+        progress
+    };
+
+    reveal(first_click, &mut visible);
+
+    loop {
+        if visible.iter().flatten().filter(|s| !is_hidden(**s)).count() >= target {
+            return true;
+        }
+
+        let deductions = deduce(&visible, Some(game.mine_points.len()));
+        let mut progress = false;
+        for p in deductions.safe {
+            if is_hidden(visible[p.x][p.y]) {
+                progress |= reveal(p, &mut visible);
+            }
+        }
+
+        if !progress {
+            return false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(rows: &[&str]) -> Vec<Vec<BoardState>> {
+        // Rows are written top to bottom for readability; the board is indexed [x][y]
+        let cols = rows[0].len();
+        let mut board = vec![vec![BoardState::Unknown; rows.len()]; cols];
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                board[x][y] = match c {
+                    '?' => BoardState::Unknown,
+                    'F' => BoardState::Flag,
+                    '*' => BoardState::Mine,
+                    '0' => BoardState::Zero,
+                    '1' => BoardState::One,
+                    '2' => BoardState::Two,
+                    '3' => BoardState::Three,
+                    _ => panic!("unexpected cell {}", c),
+                };
+            }
+        }
+        board
+    }
+
+    #[test]
+    fn single_cell_constraints_resolve_mines_and_safe_cells() {
+        let visible = board(&["01?", "01?", "01?"]);
+
+        let deductions = deduce(&visible, None);
+
+        // The middle '1' touches all three hidden cells, the edge '1's only two of them,
+        // so subset reasoning pins the mine to the middle of the column
+        assert_eq!(deductions.mines, HashSet::from([Point { x: 2, y: 1 }]));
+        assert_eq!(
+            deductions.safe,
+            HashSet::from([Point { x: 2, y: 0 }, Point { x: 2, y: 2 }])
+        );
+    }
+
+    #[test]
+    fn flags_are_not_trusted() {
+        let visible = board(&["01F", "01?", "01?"]);
+
+        let deductions = deduce(&visible, None);
+
+        assert!(deductions.safe.contains(&Point { x: 2, y: 0 }));
+        assert!(deductions.mines.contains(&Point { x: 2, y: 1 }));
+    }
+
+    #[test]
+    fn fifty_fifty_yields_no_deductions() {
+        let visible = board(&["01?", "01?"]);
+
+        let deductions = deduce(&visible, Some(1));
+
+        assert!(deductions.safe.is_empty());
+        assert!(deductions.mines.is_empty());
+    }
+
+    #[test]
+    fn global_mine_count_resolves_remaining_cells() {
+        let visible = board(&["01??", "01??"]);
+
+        let deductions = deduce(&visible, Some(1));
+
+        assert_eq!(
+            deductions.safe,
+            HashSet::from([Point { x: 3, y: 0 }, Point { x: 3, y: 1 }])
+        );
+        assert!(deductions.mines.is_empty());
+    }
+}
@@ -13,6 +13,12 @@ pub enum AppError {
     BadRequest(String),
     #[display("Unauthorized")]
     Unauthorized,
+    #[display("Forbidden: {_0}")]
+    Forbidden(String),
+    #[display("Too Many Requests: {_0}")]
+    TooManyRequests(String),
+    #[display("Conflict: {_0}")]
+    Conflict(String),
 }
 
 #[derive(Serialize)]
@@ -27,6 +33,9 @@ impl ResponseError for AppError {
             AppError::NotFound(_) => StatusCode::NOT_FOUND,
             AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
             AppError::Unauthorized => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
         }
     }
 
@@ -55,6 +64,24 @@ impl From<mongodb::bson::ser::Error> for AppError {
     }
 }
 
+impl From<sqlx::Error> for AppError {
+    fn from(e: sqlx::Error) -> Self {
+        AppError::Internal(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        AppError::Internal(e.to_string())
+    }
+}
+
+impl From<opentelemetry::metrics::MetricsError> for AppError {
+    fn from(e: opentelemetry::metrics::MetricsError) -> Self {
+        AppError::Internal(e.to_string())
+    }
+}
+
 impl From<anyhow::Error> for AppError {
     fn from(e: anyhow::Error) -> Self {
         AppError::Internal(e.to_string())
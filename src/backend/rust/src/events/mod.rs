@@ -0,0 +1,118 @@
+use actix_web::web::Bytes;
+use futures_util::{stream, Stream, StreamExt};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::Mutex;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+const CHANNEL_CAPACITY: usize = 64;
+
+/// Fan-out of updates keyed by an entity id. Channels are created on first subscription
+/// and pruned once their last subscriber has gone.
+pub struct Hub<K, T> {
+    channels: Mutex<HashMap<K, broadcast::Sender<T>>>,
+}
+
+impl<K, T> Default for Hub<K, T> {
+    fn default() -> Self {
+        Hub {
+            channels: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<K: Eq + Hash, T: Clone + Send + 'static> Hub<K, T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&self, id: K) -> broadcast::Receiver<T> {
+        let mut channels = self.channels.lock().unwrap_or_else(|e| e.into_inner());
+        channels.retain(|_, sender| sender.receiver_count() > 0);
+        channels
+            .entry(id)
+            .or_insert_with(|| broadcast::channel(CHANNEL_CAPACITY).0)
+            .subscribe()
+    }
+
+    /// Like `subscribe`, but refuses once `limit` receivers are already attached.
+    pub fn subscribe_limited(&self, id: K, limit: usize) -> Option<broadcast::Receiver<T>> {
+        let mut channels = self.channels.lock().unwrap_or_else(|e| e.into_inner());
+        channels.retain(|_, sender| sender.receiver_count() > 0);
+        let sender = channels
+            .entry(id)
+            .or_insert_with(|| broadcast::channel(CHANNEL_CAPACITY).0);
+        (sender.receiver_count() < limit).then(|| sender.subscribe())
+    }
+
+    pub fn publish(&self, id: K, value: T) {
+        let mut channels = self.channels.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(sender) = channels.get(&id) {
+            if sender.send(value).is_err() {
+                channels.remove(&id);
+            }
+        }
+    }
+}
+
+/// Server-sent event body that emits `initial` followed by every update published to
+/// `updates`. A client that falls behind gets whatever `resync` returns in place of the
+/// updates it missed, so incremental streams can send a fresh snapshot; returning `None`
+/// just carries on with the next update.
+pub fn sse_stream<T, R, Fut>(
+    initial: T,
+    updates: broadcast::Receiver<T>,
+    resync: R,
+) -> impl Stream<Item = Result<Bytes, actix_web::Error>>
+where
+    T: Serialize + Clone + Send + 'static,
+    R: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = Option<T>> + Send,
+{
+    let updates = stream::unfold((updates, resync), |(mut rx, resync)| async move {
+        loop {
+            match rx.recv().await {
+                Ok(value) => return Some((value, (rx, resync))),
+                Err(RecvError::Lagged(_)) => {
+                    if let Some(value) = resync().await {
+                        return Some((value, (rx, resync)));
+                    }
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    stream::once(async move { initial })
+        .chain(updates)
+        .map(|value| {
+            let json = serde_json::to_string(&value)?;
+            Ok(Bytes::from(format!("data: {}\n\n", json)))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn lagging_clients_get_the_resync_value() {
+        let (tx, rx) = broadcast::channel(2);
+        for value in 1..=3 {
+            tx.send(value).unwrap();
+        }
+        drop(tx);
+
+        let events: Vec<String> = sse_stream(0, rx, || async { Some(99) })
+            .map(|event| String::from_utf8(event.unwrap().to_vec()).unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(
+            events,
+            ["data: 0\n\n", "data: 99\n\n", "data: 2\n\n", "data: 3\n\n"]
+        );
+    }
+}
@@ -2,6 +2,7 @@ pub mod api;
 pub mod auth;
 pub mod engine;
 pub mod error;
+pub mod events;
 pub mod model;
 pub mod repository;
 pub mod service;
@@ -5,7 +5,7 @@ use rust_backend::settings::Settings;
 use rust_backend::startup::Application;
 use rust_backend::{auth, repository, telemetry};
 use std::sync::Arc;
-use tracing::info;
+use tracing::{error, info};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -13,13 +13,21 @@ async fn main() -> std::io::Result<()> {
     let settings = Settings::new().expect("Failed to load settings");
     telemetry::init_telemetry(&settings);
 
-    let repo = repository::init_repository(&settings.database).await;
+    let (repo, repo_status) = repository::init_repository(&settings.database)
+        .await
+        .map_err(|e| {
+            error!("{:#}", e);
+            std::io::Error::other(e.to_string())
+        })?;
     let repo_data = web::Data::new(repo.clone());
+    let repo_status_data = web::Data::new(repo_status);
 
     let engine = Arc::new(MinesweeperEngine);
-    let game_service: Arc<dyn rust_backend::service::GameService> =
-        Arc::new(MinesweeperService::new(repo.clone(), engine));
+    let service = Arc::new(MinesweeperService::new(repo.clone(), engine));
+    let game_service: Arc<dyn rust_backend::service::GameService> = service.clone();
+    let match_service: Arc<dyn rust_backend::service::MatchService> = service;
     let service_data = web::Data::new(game_service);
+    let match_service_data = web::Data::new(match_service);
 
     let google_client = auth::init_google_client(&settings).await;
     let session_key = auth::get_session_key(&settings);
@@ -28,7 +36,9 @@ async fn main() -> std::io::Result<()> {
 
     let app = Application::build(
         repo_data,
+        repo_status_data,
         service_data,
+        match_service_data,
         google_client,
         settings,
         session_key,
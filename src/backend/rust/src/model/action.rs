@@ -0,0 +1,52 @@
+use super::board::Point;
+use super::id::UserId;
+use super::user::UserInfo;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActionKind {
+    Reveal,
+    Chord,
+    Flag,
+    Unflag,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GameAction {
+    pub kind: ActionKind,
+    pub point: Point,
+    /// Cells this action revealed that were hidden before it.
+    #[serde(default)]
+    pub revealed: Vec<Point>,
+    pub at: DateTime<Utc>,
+    /// The player who made the move, for games shared between several users.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<UserId>,
+}
+
+impl GameAction {
+    pub fn new(kind: ActionKind, point: Point, revealed: Vec<Point>) -> Self {
+        GameAction {
+            kind,
+            point,
+            revealed,
+            at: Utc::now(),
+            user_id: None,
+        }
+    }
+
+    /// Cells whose visible state this action changed.
+    pub fn changed_points(&self) -> Vec<Point> {
+        match self.kind {
+            ActionKind::Reveal | ActionKind::Chord => self.revealed.clone(),
+            ActionKind::Flag | ActionKind::Unflag => vec![self.point],
+        }
+    }
+
+    pub fn with_user(mut self, user: Option<&UserInfo>) -> Self {
+        self.user_id = user.map(|u| u.sub.clone());
+        self
+    }
+}
@@ -1,5 +1,7 @@
+use super::action::GameAction;
 use super::board::{BoardState, Point};
 use super::game::{GameStatus, MinesweeperGame};
+use super::id::{GameId, MatchId, UserId};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
@@ -7,45 +9,196 @@ use std::collections::HashSet;
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct MinesweeperGameDto {
-    pub id: i32,
+    pub id: GameId,
     pub board: Vec<Vec<BoardState>>,
     pub mine_count: usize,
     pub flag_points: HashSet<Point>,
     pub status: GameStatus,
     pub created_at: DateTime<Utc>,
+    pub seed: Option<u32>,
+    pub no_guess: bool,
+    pub hints_used: u32,
+    pub practice: bool,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub elapsed_ms: i64,
+    pub match_id: Option<MatchId>,
+    pub participant_count: usize,
+    /// Account ids of invited players, only filled in for the owner.
+    pub participants: Option<Vec<UserId>>,
+    pub private: bool,
 }
 
 impl From<&MinesweeperGame> for MinesweeperGameDto {
     fn from(game: &MinesweeperGame) -> Self {
-        let mut dto_board = vec![vec![BoardState::Unknown; game.rows]; game.cols];
+        let status = game.status();
 
-        // Apply Flags
-        for flag in &game.flag_points {
-            dto_board[flag.x][flag.y] = BoardState::Flag;
+        MinesweeperGameDto {
+            id: game.id,
+            board: game.visible_board(),
+            mine_count: game.mine_count(),
+            flag_points: game.flag_points.clone(),
+            status,
+            created_at: game.created_at,
+            // The seed fully determines the layout, so only reveal it once play is over
+            seed: game.seed.filter(|_| status != GameStatus::InProgress),
+            no_guess: game.no_guess,
+            hints_used: game.hints_used,
+            practice: game.practice,
+            started_at: game.started_at,
+            finished_at: game.finished_at,
+            elapsed_ms: game.elapsed_ms(),
+            match_id: game.match_id,
+            participant_count: game.participants.len(),
+            participants: None,
+            private: game.private,
         }
+    }
+}
 
-        // Apply Moves
-        for mv in &game.moves {
-            dto_board[mv.x][mv.y] = game.board[mv.x][mv.y];
+impl MinesweeperGameDto {
+    /// The game as `viewer` may see it: only the owner learns who else was invited.
+    pub fn for_viewer(game: &MinesweeperGame, viewer: Option<&UserId>) -> Self {
+        let mut dto = Self::from(game);
+        if viewer.is_some_and(|v| game.owner.as_ref() == Some(v)) {
+            dto.participants = Some(game.participants.clone());
         }
+        dto
+    }
+}
 
-        let status = if game.is_game_lost() {
-            GameStatus::Lost
-        } else if game.is_game_won() {
-            GameStatus::Won
-        } else {
-            GameStatus::InProgress
-        };
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HintDto {
+    pub point: Option<Point>,
+    pub hints_used: u32,
+}
 
-        MinesweeperGameDto {
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProbabilitiesDto {
+    pub id: GameId,
+    pub probabilities: Vec<Vec<Option<f64>>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayDto {
+    pub id: GameId,
+    pub seed: Option<u32>,
+    pub cols: usize,
+    pub rows: usize,
+    pub mine_count: usize,
+    pub no_guess: bool,
+    pub board: Vec<Vec<BoardState>>,
+    pub actions: Vec<GameAction>,
+    pub step: Option<usize>,
+    pub visible_board: Option<Vec<Vec<BoardState>>>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct ReplayQuery {
+    pub step: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellDto {
+    pub x: usize,
+    pub y: usize,
+    pub state: BoardState,
+}
+
+/// The cells changed by a single action along with the counters a client needs to keep
+/// its copy of the board in sync.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GameUpdateDto {
+    pub id: GameId,
+    pub cells: Vec<CellDto>,
+    pub status: GameStatus,
+    pub mine_count: usize,
+    pub flag_count: usize,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub user_id: Option<UserId>,
+}
+
+impl GameUpdateDto {
+    pub fn new(game: &MinesweeperGame, changed: &[Point], user_id: Option<UserId>) -> Self {
+        let mut cells: Vec<CellDto> = changed
+            .iter()
+            .map(|p| CellDto {
+                x: p.x,
+                y: p.y,
+                state: game.visible_state(p),
+            })
+            .collect();
+        cells.sort_by_key(|c| (c.x, c.y));
+
+        GameUpdateDto {
             id: game.id,
-            board: dto_board,
+            cells,
+            status: game.status(),
             mine_count: game.mine_count(),
-            flag_points: game.flag_points.clone(),
-            status,
-            created_at: game.created_at,
+            flag_count: game.flag_points.len(),
+            finished_at: game.finished_at,
+            user_id,
         }
     }
+
+    /// Every cell the players can currently see, as a single update.
+    pub fn snapshot(game: &MinesweeperGame) -> Self {
+        let visible: Vec<Point> = game
+            .moves
+            .iter()
+            .chain(game.flag_points.iter())
+            .cloned()
+            .collect();
+        GameUpdateDto::new(game, &visible, None)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VisibilityRequest {
+    pub private: bool,
+}
+
+/// Commands a client can send over the live game socket.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum LiveCommand {
+    Move { x: usize, y: usize },
+    Flag { x: usize, y: usize },
+    Chord { x: usize, y: usize },
+}
+
+/// Messages pushed to live game clients. A snapshot is sent on connect and whenever the
+/// client has fallen too far behind to apply updates incrementally.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum LiveMessage {
+    Snapshot { game: MinesweeperGameDto },
+    Update(GameUpdateDto),
+    Error { message: String },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InviteRequest {
+    pub sub: UserId,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct InviteCodeDto {
+    pub id: GameId,
+    pub invite_code: String,
+}
+
+/// `?diff=true` asks move endpoints for a `GameUpdateDto` with only the changed cells
+/// instead of the full board.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct MoveQuery {
+    #[serde(default)]
+    pub diff: bool,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -53,5 +206,5 @@ pub struct MakeMoveRequest {
     pub x: usize,
     pub y: usize,
     #[serde(rename = "gameId")]
-    pub game_id: Option<i32>,
+    pub game_id: Option<GameId>,
 }
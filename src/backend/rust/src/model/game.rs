@@ -1,4 +1,6 @@
+use super::action::{ActionKind, GameAction};
 use super::board::{BoardState, Point};
+use super::id::{GameId, MatchId, UserId};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
@@ -14,7 +16,7 @@ pub enum GameStatus {
 #[serde(rename_all = "PascalCase")]
 pub struct MinesweeperGame {
     #[serde(rename = "_id")]
-    pub id: i32,
+    pub id: GameId,
     pub board: Vec<Vec<BoardState>>,
     pub moves: HashSet<Point>,
     pub mine_points: HashSet<Point>,
@@ -24,13 +26,57 @@ pub struct MinesweeperGame {
     pub cols: usize,
     pub rows: usize,
     pub mine_count_target: usize,
+    /// `None` for games stored before seeds were introduced.
+    #[serde(default)]
+    pub seed: Option<u32>,
+    #[serde(default)]
+    pub no_guess: bool,
+    #[serde(default)]
+    pub hints_used: u32,
+    #[serde(default)]
+    pub practice: bool,
+    #[serde(default)]
+    pub started_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub finished_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub actions: Vec<GameAction>,
+    #[serde(default)]
+    pub match_id: Option<MatchId>,
+    /// The signed-in user who created the game; anonymous games have none.
+    #[serde(default)]
+    pub owner: Option<UserId>,
+    /// Users the owner has invited to play this board alongside them.
+    #[serde(default)]
+    pub participants: Vec<UserId>,
+    #[serde(default)]
+    pub invite_code: Option<String>,
+    /// Private games can only be watched by their players.
+    #[serde(default)]
+    pub private: bool,
+    /// Incremented by every write; conditional writes are rejected once it has moved on.
+    #[serde(default)]
+    pub version: i64,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct GameOptions {
+    pub seed: Option<u32>,
+    #[serde(default)]
+    pub no_guess: bool,
+    #[serde(default)]
+    pub practice: bool,
+    #[serde(default)]
+    pub private: bool,
 }
 
 impl MinesweeperGame {
-    pub fn new(cols: usize, rows: usize, mines: usize) -> Self {
+    pub fn new(cols: usize, rows: usize, mines: usize, options: &GameOptions) -> Self {
         use rand::Rng;
+        let mut rng = rand::thread_rng();
         MinesweeperGame {
-            id: rand::thread_rng().gen_range(1..i32::MAX),
+            id: GameId::random(),
             board: vec![vec![BoardState::Zero; rows]; cols],
             moves: HashSet::new(),
             mine_points: HashSet::new(),
@@ -40,6 +86,19 @@ impl MinesweeperGame {
             cols,
             rows,
             mine_count_target: mines,
+            seed: Some(options.seed.unwrap_or_else(|| rng.gen())),
+            no_guess: options.no_guess,
+            hints_used: 0,
+            practice: options.practice,
+            started_at: None,
+            finished_at: None,
+            actions: Vec::new(),
+            match_id: None,
+            owner: None,
+            participants: Vec::new(),
+            invite_code: None,
+            private: options.private,
+            version: 0,
         }
     }
 
@@ -55,10 +114,44 @@ impl MinesweeperGame {
         p.x < self.cols && p.y < self.rows
     }
 
+    pub fn neighbours(&self, p: &Point) -> Vec<Point> {
+        let mut points = Vec::with_capacity(8);
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                if dx == 0 && dy == 0 {
+                    continue;
+                }
+
+                let nx = p.x as isize + dx;
+                let ny = p.y as isize + dy;
+
+                if nx >= 0 && nx < self.cols as isize && ny >= 0 && ny < self.rows as isize {
+                    points.push(Point {
+                        x: nx as usize,
+                        y: ny as usize,
+                    });
+                }
+            }
+        }
+        points
+    }
+
     pub fn is_point_revealed(&self, p: &Point) -> bool {
         self.moves.contains(p)
     }
 
+    pub fn unrevealed(&self, points: &[Point]) -> Vec<Point> {
+        points
+            .iter()
+            .filter(|p| !self.is_point_revealed(p))
+            .cloned()
+            .collect()
+    }
+
+    pub fn is_participant(&self, user_id: &UserId) -> bool {
+        self.participants.iter().any(|p| p == user_id)
+    }
+
     pub fn is_point_flagged(&self, p: &Point) -> bool {
         self.flag_points.contains(p)
     }
@@ -83,4 +176,74 @@ impl MinesweeperGame {
     pub fn is_game_over(&self) -> bool {
         self.is_game_won() || self.is_game_lost()
     }
+
+    pub fn elapsed_ms(&self) -> i64 {
+        match self.started_at {
+            Some(started) => (self.finished_at.unwrap_or_else(Utc::now) - started)
+                .num_milliseconds()
+                .max(0),
+            None => 0,
+        }
+    }
+
+    /// Analysis tools that leak mine positions are limited to practice or finished games.
+    pub fn allows_analysis(&self) -> bool {
+        self.practice || self.is_game_over()
+    }
+
+    /// Reverts the most recent action, returning it, and reopens the game if that action
+    /// had finished it.
+    pub fn undo_last_action(&mut self) -> Option<GameAction> {
+        let action = self.actions.pop()?;
+        match action.kind {
+            ActionKind::Reveal | ActionKind::Chord => {
+                for p in &action.revealed {
+                    self.moves.remove(p);
+                }
+            }
+            ActionKind::Flag => {
+                self.flag_points.remove(&action.point);
+            }
+            ActionKind::Unflag => {
+                self.flag_points.insert(action.point);
+            }
+        }
+        self.finished_at = None;
+        Some(action)
+    }
+
+    pub fn status(&self) -> GameStatus {
+        if self.is_game_lost() {
+            GameStatus::Lost
+        } else if self.is_game_won() {
+            GameStatus::Won
+        } else {
+            GameStatus::InProgress
+        }
+    }
+
+    pub fn visible_state(&self, p: &Point) -> BoardState {
+        if self.is_point_revealed(p) {
+            self.board[p.x][p.y]
+        } else if self.is_point_flagged(p) {
+            BoardState::Flag
+        } else {
+            BoardState::Unknown
+        }
+    }
+
+    /// The board as the player sees it: hidden cells are `Unknown` or `Flag`.
+    pub fn visible_board(&self) -> Vec<Vec<BoardState>> {
+        let mut board = vec![vec![BoardState::Unknown; self.rows]; self.cols];
+
+        for flag in &self.flag_points {
+            board[flag.x][flag.y] = BoardState::Flag;
+        }
+
+        for mv in &self.moves {
+            board[mv.x][mv.y] = self.board[mv.x][mv.y];
+        }
+
+        board
+    }
 }
@@ -0,0 +1,132 @@
+use crate::settings::DatabaseBackend;
+use serde::Serialize;
+
+/// Outcome of repository initialisation. A degraded repository is the in-memory
+/// fallback standing in for an unreachable configured backend.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RepositoryStatus {
+    pub configured: DatabaseBackend,
+    pub active: DatabaseBackend,
+    pub degraded: bool,
+    pub error: Option<String>,
+}
+
+impl RepositoryStatus {
+    pub fn healthy(backend: DatabaseBackend) -> Self {
+        RepositoryStatus {
+            configured: backend,
+            active: backend,
+            degraded: false,
+            error: None,
+        }
+    }
+
+    pub fn fallback(configured: DatabaseBackend, error: String) -> Self {
+        RepositoryStatus {
+            configured,
+            active: DatabaseBackend::Memory,
+            degraded: true,
+            error: Some(error),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthState {
+    Ok,
+    Degraded,
+    Down,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthDto {
+    pub status: HealthState,
+    pub repository: RepositoryStatus,
+}
+
+impl HealthDto {
+    pub fn new(repository: &RepositoryStatus) -> Self {
+        HealthDto {
+            status: if repository.degraded {
+                HealthState::Degraded
+            } else {
+                HealthState::Ok
+            },
+            repository: repository.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LivenessDto {
+    pub status: HealthState,
+}
+
+/// Outcome of probing one dependency. Latency is only reported for dependencies that
+/// are actually contacted.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DependencyHealth {
+    pub status: HealthState,
+    pub latency_ms: Option<f64>,
+    pub error: Option<String>,
+}
+
+impl DependencyHealth {
+    pub fn ok(latency_ms: Option<f64>) -> Self {
+        DependencyHealth {
+            status: HealthState::Ok,
+            latency_ms,
+            error: None,
+        }
+    }
+
+    pub fn degraded(latency_ms: Option<f64>, error: String) -> Self {
+        DependencyHealth {
+            status: HealthState::Degraded,
+            latency_ms,
+            error: Some(error),
+        }
+    }
+
+    pub fn down(latency_ms: Option<f64>, error: String) -> Self {
+        DependencyHealth {
+            status: HealthState::Down,
+            latency_ms,
+            error: Some(error),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadinessDto {
+    pub status: HealthState,
+    pub repository: DependencyHealth,
+    pub oauth: DependencyHealth,
+}
+
+impl ReadinessDto {
+    /// Only an unreachable repository makes the server unready; without an OAuth client
+    /// games still work, just not sign-in.
+    pub fn new(repository: DependencyHealth, oauth: DependencyHealth) -> Self {
+        let status = match (repository.status, oauth.status) {
+            (HealthState::Down, _) => HealthState::Down,
+            (HealthState::Ok, HealthState::Ok) => HealthState::Ok,
+            _ => HealthState::Degraded,
+        };
+        ReadinessDto {
+            status,
+            repository,
+            oauth,
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.status != HealthState::Down
+    }
+}
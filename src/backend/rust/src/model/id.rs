@@ -0,0 +1,134 @@
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+
+/// Id of a stored game. Ids are always positive, so `0` and negatives are rejected
+/// wherever one is deserialized, request paths and bodies included.
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    Display,
+    sqlx::Type,
+)]
+#[serde(try_from = "i32", into = "i32")]
+#[sqlx(transparent)]
+pub struct GameId(i32);
+
+impl GameId {
+    pub fn random() -> Self {
+        use rand::Rng;
+        GameId(rand::thread_rng().gen_range(1..i32::MAX))
+    }
+
+    pub fn get(self) -> i32 {
+        self.0
+    }
+}
+
+impl TryFrom<i32> for GameId {
+    type Error = String;
+
+    fn try_from(id: i32) -> Result<Self, Self::Error> {
+        if id > 0 {
+            Ok(GameId(id))
+        } else {
+            Err(format!("Invalid game id: {}", id))
+        }
+    }
+}
+
+impl From<GameId> for i32 {
+    fn from(id: GameId) -> Self {
+        id.0
+    }
+}
+
+/// Id of a versus match, validated the same way as `GameId`.
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    Display,
+    sqlx::Type,
+)]
+#[serde(try_from = "i32", into = "i32")]
+#[sqlx(transparent)]
+pub struct MatchId(i32);
+
+impl MatchId {
+    pub fn random() -> Self {
+        use rand::Rng;
+        MatchId(rand::thread_rng().gen_range(1..i32::MAX))
+    }
+
+    pub fn get(self) -> i32 {
+        self.0
+    }
+}
+
+impl TryFrom<i32> for MatchId {
+    type Error = String;
+
+    fn try_from(id: i32) -> Result<Self, Self::Error> {
+        if id > 0 {
+            Ok(MatchId(id))
+        } else {
+            Err(format!("Invalid match id: {}", id))
+        }
+    }
+}
+
+impl From<MatchId> for i32 {
+    fn from(id: MatchId) -> Self {
+        id.0
+    }
+}
+
+/// The `sub` claim identifying a signed-in user.
+#[derive(
+    Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Display, sqlx::Type,
+)]
+#[serde(transparent)]
+#[sqlx(transparent)]
+pub struct UserId(String);
+
+impl UserId {
+    pub fn new(sub: impl Into<String>) -> Self {
+        UserId(sub.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn game_ids_must_be_positive() {
+        assert_eq!(serde_json::from_str::<GameId>("42").unwrap().get(), 42);
+        assert!(serde_json::from_str::<GameId>("0").is_err());
+        assert!(serde_json::from_str::<GameId>("-7").is_err());
+        assert_eq!(serde_json::to_string(&GameId(42)).unwrap(), "42");
+    }
+}
@@ -1,9 +1,26 @@
+pub mod action;
 pub mod board;
 pub mod dto;
 pub mod game;
+pub mod health;
+pub mod id;
 pub mod user;
+pub mod versus;
 
+pub use action::{ActionKind, GameAction};
 pub use board::{BoardState, Point};
-pub use dto::{MakeMoveRequest, MinesweeperGameDto};
-pub use game::{GameStatus, MinesweeperGame};
-pub use user::{UserInfo, UserStatsDto};
+pub use dto::{
+    CellDto, GameUpdateDto, HintDto, InviteCodeDto, InviteRequest, LiveCommand, LiveMessage,
+    MakeMoveRequest, MinesweeperGameDto, MoveQuery, ProbabilitiesDto, ReplayDto, ReplayQuery,
+    VisibilityRequest,
+};
+pub use game::{GameOptions, GameStatus, MinesweeperGame};
+pub use health::{
+    DependencyHealth, HealthDto, HealthState, LivenessDto, ReadinessDto, RepositoryStatus,
+};
+pub use id::{GameId, MatchId, UserId};
+pub use user::{BestTimeDto, UserInfo, UserStatsDto};
+pub use versus::{
+    MatchDto, MatchOutcome, MatchPlayer, MatchPlayerDto, MatchStatus, VersusMatch,
+    MAX_MATCH_PLAYERS,
+};
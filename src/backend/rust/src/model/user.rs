@@ -1,8 +1,9 @@
+use super::id::UserId;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct UserInfo {
-    pub sub: String,
+    pub sub: UserId,
     pub name: Option<String>,
     pub email: Option<String>,
 }
@@ -13,4 +14,15 @@ pub struct UserStatsDto {
     pub won: i32,
     pub lost: i32,
     pub in_progress: i32,
+    pub assisted_won: i32,
+    pub best_times: Vec<BestTimeDto>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BestTimeDto {
+    pub cols: usize,
+    pub rows: usize,
+    pub mines: usize,
+    pub elapsed_ms: i64,
 }
@@ -0,0 +1,256 @@
+use super::game::{GameStatus, MinesweeperGame};
+use super::id::{GameId, MatchId, UserId};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+pub const MAX_MATCH_PLAYERS: usize = 8;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchStatus {
+    Waiting,
+    InProgress,
+    Finished,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "PascalCase")]
+pub struct MatchPlayer {
+    pub user_id: UserId,
+    /// The player's own copy of the board, created when the match starts.
+    pub game_id: Option<GameId>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct VersusMatch {
+    #[serde(rename = "_id")]
+    pub id: MatchId,
+    pub owner: UserId,
+    pub cols: usize,
+    pub rows: usize,
+    pub mines: usize,
+    pub seed: u32,
+    pub status: MatchStatus,
+    pub players: Vec<MatchPlayer>,
+    pub winner: Option<UserId>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl VersusMatch {
+    pub fn new(owner: &UserId, cols: usize, rows: usize, mines: usize) -> Self {
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+        VersusMatch {
+            id: MatchId::random(),
+            owner: owner.clone(),
+            cols,
+            rows,
+            mines,
+            seed: rng.gen(),
+            status: MatchStatus::Waiting,
+            players: vec![MatchPlayer {
+                user_id: owner.clone(),
+                game_id: None,
+            }],
+            winner: None,
+            created_at: Utc::now(),
+            started_at: None,
+            finished_at: None,
+        }
+    }
+
+    pub fn has_player(&self, user_id: &UserId) -> bool {
+        self.players.iter().any(|p| &p.user_id == user_id)
+    }
+
+    pub fn player_for_game(&self, game_id: GameId) -> Option<&MatchPlayer> {
+        self.players.iter().find(|p| p.game_id == Some(game_id))
+    }
+}
+
+/// Revealed safe cells; every player starts from the same auto-revealed opening.
+pub fn progress(game: &MinesweeperGame) -> usize {
+    game.moves
+        .iter()
+        .filter(|p| !game.mine_points.contains(p))
+        .count()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MatchOutcome {
+    Winner(UserId),
+    Draw,
+}
+
+/// Decides a match once one of its games is over: whoever clears their board first wins
+/// outright, otherwise the surviving player with the most progress wins as soon as
+/// someone hits a mine. Hitting a mine forfeits, so the progress of players who did is
+/// never compared, however far ahead they were. Ties between survivors, or nobody
+/// surviving, are a draw.
+pub fn decide_outcome(versus: &VersusMatch, games: &[MinesweeperGame]) -> Option<MatchOutcome> {
+    let outcome_for = |game: &MinesweeperGame| match versus.player_for_game(game.id) {
+        Some(p) => MatchOutcome::Winner(p.user_id.clone()),
+        None => MatchOutcome::Draw,
+    };
+
+    if let Some(cleared) = games.iter().find(|g| g.is_game_won()) {
+        return Some(outcome_for(cleared));
+    }
+
+    if !games.iter().any(|g| g.is_game_lost()) {
+        return None;
+    }
+
+    let survivors: Vec<&MinesweeperGame> = games.iter().filter(|g| !g.is_game_lost()).collect();
+    let best = survivors.iter().map(|g| progress(g)).max();
+    let leaders: Vec<&MinesweeperGame> = survivors
+        .into_iter()
+        .filter(|g| Some(progress(g)) == best)
+        .collect();
+
+    match leaders.as_slice() {
+        [leader] => Some(outcome_for(leader)),
+        _ => Some(MatchOutcome::Draw),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchPlayerDto {
+    pub user_id: UserId,
+    pub game_id: Option<GameId>,
+    pub progress: usize,
+    pub status: Option<GameStatus>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchDto {
+    pub id: MatchId,
+    pub owner: UserId,
+    pub cols: usize,
+    pub rows: usize,
+    pub mines: usize,
+    /// Safe cells a player has to reveal to clear the board.
+    pub target: usize,
+    pub status: MatchStatus,
+    pub players: Vec<MatchPlayerDto>,
+    pub winner: Option<UserId>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl MatchDto {
+    pub fn new(versus: &VersusMatch, games: &[MinesweeperGame]) -> Self {
+        let players = versus
+            .players
+            .iter()
+            .map(|p| {
+                let game = games.iter().find(|g| Some(g.id) == p.game_id);
+                MatchPlayerDto {
+                    user_id: p.user_id.clone(),
+                    game_id: p.game_id,
+                    progress: game.map_or(0, progress),
+                    status: game.map(|g| g.status()),
+                }
+            })
+            .collect();
+
+        MatchDto {
+            id: versus.id,
+            owner: versus.owner.clone(),
+            cols: versus.cols,
+            rows: versus.rows,
+            mines: versus.mines,
+            target: (versus.cols * versus.rows).saturating_sub(versus.mines),
+            status: versus.status,
+            players,
+            winner: versus.winner.clone(),
+            created_at: versus.created_at,
+            started_at: versus.started_at,
+            finished_at: versus.finished_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Point;
+
+    /// A 4x1 board with its single mine in the last cell and the first `revealed` cells open.
+    fn game(
+        versus: &mut VersusMatch,
+        player: &str,
+        revealed: usize,
+        hit_mine: bool,
+    ) -> MinesweeperGame {
+        let mut game = MinesweeperGame::new(4, 1, 1, &Default::default());
+        game.mines_generated = true;
+        game.mine_points.insert(Point { x: 3, y: 0 });
+        game.moves.extend((0..revealed).map(|x| Point { x, y: 0 }));
+        if hit_mine {
+            game.moves.insert(Point { x: 3, y: 0 });
+        }
+
+        let user_id = UserId::new(player);
+        if !versus.has_player(&user_id) {
+            versus.players.push(MatchPlayer {
+                user_id: user_id.clone(),
+                game_id: None,
+            });
+        }
+        let slot = versus
+            .players
+            .iter_mut()
+            .find(|p| p.user_id == user_id)
+            .unwrap();
+        slot.game_id = Some(game.id);
+        game
+    }
+
+    #[test]
+    fn hitting_a_mine_forfeits_regardless_of_progress() {
+        let mut versus = VersusMatch::new(&UserId::new("alice"), 4, 1, 1);
+        let games = [
+            game(&mut versus, "alice", 2, true),
+            game(&mut versus, "bob", 1, false),
+        ];
+
+        assert_eq!(
+            decide_outcome(&versus, &games),
+            Some(MatchOutcome::Winner(UserId::new("bob")))
+        );
+    }
+
+    #[test]
+    fn tied_or_missing_survivors_draw() {
+        let mut versus = VersusMatch::new(&UserId::new("alice"), 4, 1, 1);
+        let tied = [
+            game(&mut versus, "alice", 1, true),
+            game(&mut versus, "bob", 1, false),
+            game(&mut versus, "carol", 1, false),
+        ];
+        assert_eq!(decide_outcome(&versus, &tied), Some(MatchOutcome::Draw));
+
+        let all_lost = [
+            game(&mut versus, "alice", 1, true),
+            game(&mut versus, "bob", 2, true),
+        ];
+        assert_eq!(decide_outcome(&versus, &all_lost), Some(MatchOutcome::Draw));
+    }
+
+    #[test]
+    fn matches_continue_until_someone_finishes() {
+        let mut versus = VersusMatch::new(&UserId::new("alice"), 4, 1, 1);
+        let games = [
+            game(&mut versus, "alice", 2, false),
+            game(&mut versus, "bob", 1, false),
+        ];
+
+        assert_eq!(decide_outcome(&versus, &games), None);
+    }
+}
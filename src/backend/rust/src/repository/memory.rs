@@ -1,14 +1,19 @@
 use crate::error::{AppError, AppResult};
-use crate::model::{MinesweeperGame, Point};
-use crate::repository::{GameRepository, UserGameRepository};
+use crate::model::{
+    GameAction, GameId, MatchId, MatchPlayer, MatchStatus, MinesweeperGame, Point, UserId,
+    VersusMatch, MAX_MATCH_PLAYERS,
+};
+use crate::repository::{GameRepository, MatchRepository, UserGameRepository};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 
 #[derive(Clone)]
 pub struct InMemoryGameRepository {
-    games: Arc<RwLock<HashMap<i32, MinesweeperGame>>>,
-    user_games: Arc<RwLock<HashMap<String, Vec<i32>>>>,
+    games: Arc<RwLock<HashMap<GameId, MinesweeperGame>>>,
+    user_games: Arc<RwLock<HashMap<UserId, Vec<GameId>>>>,
+    matches: Arc<RwLock<HashMap<MatchId, VersusMatch>>>,
 }
 
 impl Default for InMemoryGameRepository {
@@ -16,6 +21,7 @@ impl Default for InMemoryGameRepository {
         InMemoryGameRepository {
             games: Arc::new(RwLock::new(HashMap::new())),
             user_games: Arc::new(RwLock::new(HashMap::new())),
+            matches: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 }
@@ -25,7 +31,14 @@ impl InMemoryGameRepository {
         Self::default()
     }
 
-    fn update_game<F>(&self, id: i32, f: F) -> AppResult<Option<MinesweeperGame>>
+    /// Applies `f` and bumps the version, provided the game is still at `expected` when
+    /// one is given.
+    fn update_game<F>(
+        &self,
+        id: GameId,
+        expected: Option<i64>,
+        f: F,
+    ) -> AppResult<Option<MinesweeperGame>>
     where
         F: FnOnce(&mut MinesweeperGame),
     {
@@ -33,18 +46,41 @@ impl InMemoryGameRepository {
             .games
             .write()
             .map_err(|e| AppError::Internal(e.to_string()))?;
-        if let Some(game) = games.get_mut(&id) {
-            f(game);
-            Ok(Some(game.clone()))
-        } else {
-            Ok(None)
+        match games.get_mut(&id) {
+            Some(game) if expected.is_some_and(|v| v != game.version) => {
+                Err(AppError::Conflict(id.to_string()))
+            }
+            Some(game) => {
+                f(game);
+                game.version += 1;
+                Ok(Some(game.clone()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn update_match<F>(&self, id: MatchId, f: F) -> AppResult<Option<VersusMatch>>
+    where
+        F: FnOnce(&mut VersusMatch) -> bool,
+    {
+        let mut matches = self
+            .matches
+            .write()
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        match matches.get_mut(&id) {
+            Some(versus) => Ok(f(versus).then(|| versus.clone())),
+            None => Ok(None),
         }
     }
 }
 
 #[async_trait]
 impl GameRepository for InMemoryGameRepository {
-    async fn get_game(&self, id: i32) -> AppResult<Option<MinesweeperGame>> {
+    async fn ping(&self) -> AppResult<()> {
+        Ok(())
+    }
+
+    async fn get_game(&self, id: GameId) -> AppResult<Option<MinesweeperGame>> {
         let games = self
             .games
             .read()
@@ -52,7 +88,7 @@ impl GameRepository for InMemoryGameRepository {
         Ok(games.get(&id).cloned())
     }
 
-    async fn get_games_by_ids(&self, ids: &[i32]) -> AppResult<Vec<MinesweeperGame>> {
+    async fn get_games_by_ids(&self, ids: &[GameId]) -> AppResult<Vec<MinesweeperGame>> {
         let games = self
             .games
             .read()
@@ -60,85 +96,263 @@ impl GameRepository for InMemoryGameRepository {
         Ok(ids.iter().filter_map(|id| games.get(id).cloned()).collect())
     }
 
-    async fn save(&self, game: MinesweeperGame) -> AppResult<()> {
+    async fn save(&self, mut game: MinesweeperGame) -> AppResult<MinesweeperGame> {
         let mut games = self
             .games
             .write()
             .map_err(|e| AppError::Internal(e.to_string()))?;
-        games.insert(game.id, game);
-        Ok(())
+        if games
+            .get(&game.id)
+            .is_some_and(|stored| stored.version != game.version)
+        {
+            return Err(AppError::Conflict(game.id.to_string()));
+        }
+        game.version += 1;
+        games.insert(game.id, game.clone());
+        Ok(game)
+    }
+
+    async fn insert(&self, mut game: MinesweeperGame) -> AppResult<Option<MinesweeperGame>> {
+        let mut games = self
+            .games
+            .write()
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        if games.contains_key(&game.id) {
+            return Ok(None);
+        }
+        game.version = 1;
+        games.insert(game.id, game.clone());
+        Ok(Some(game))
     }
 
-    async fn add_moves(&self, id: i32, points: &[Point]) -> AppResult<Option<MinesweeperGame>> {
-        self.update_game(id, |game| {
+    async fn add_moves(
+        &self,
+        id: GameId,
+        version: i64,
+        points: &[Point],
+        action: &GameAction,
+    ) -> AppResult<Option<MinesweeperGame>> {
+        self.update_game(id, Some(version), |game| {
             for p in points {
                 game.moves.insert(*p);
             }
+            game.actions.push(action.clone());
         })
     }
 
-    async fn add_flag(&self, id: i32, point: Point) -> AppResult<Option<MinesweeperGame>> {
-        self.update_game(id, |game| {
+    async fn add_flag(
+        &self,
+        id: GameId,
+        version: i64,
+        point: Point,
+        action: &GameAction,
+    ) -> AppResult<Option<MinesweeperGame>> {
+        self.update_game(id, Some(version), |game| {
             game.flag_points.insert(point);
+            game.actions.push(action.clone());
         })
     }
 
-    async fn remove_flag(&self, id: i32, point: Point) -> AppResult<Option<MinesweeperGame>> {
-        self.update_game(id, |game| {
+    async fn remove_flag(
+        &self,
+        id: GameId,
+        version: i64,
+        point: Point,
+        action: &GameAction,
+    ) -> AppResult<Option<MinesweeperGame>> {
+        self.update_game(id, Some(version), |game| {
             game.flag_points.remove(&point);
+            game.actions.push(action.clone());
+        })
+    }
+
+    async fn add_participant(
+        &self,
+        id: GameId,
+        user_id: &UserId,
+    ) -> AppResult<Option<MinesweeperGame>> {
+        self.update_game(id, None, |game| {
+            if !game.is_participant(user_id) {
+                game.participants.push(user_id.clone());
+            }
+        })
+    }
+
+    async fn set_invite_code(&self, id: GameId, code: &str) -> AppResult<Option<MinesweeperGame>> {
+        let mut games = self
+            .games
+            .write()
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        if let Some(holder) = games
+            .values()
+            .find(|g| g.id != id && g.invite_code.as_deref() == Some(code))
+        {
+            return Err(AppError::Conflict(format!(
+                "Invite code of game {}",
+                holder.id
+            )));
+        }
+        Ok(games.get_mut(&id).map(|game| {
+            game.invite_code = Some(code.to_string());
+            game.version += 1;
+            game.clone()
+        }))
+    }
+
+    async fn set_private(&self, id: GameId, private: bool) -> AppResult<Option<MinesweeperGame>> {
+        self.update_game(id, None, |game| {
+            game.private = private;
+        })
+    }
+
+    async fn get_game_by_invite_code(&self, code: &str) -> AppResult<Option<MinesweeperGame>> {
+        let games = self
+            .games
+            .read()
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        Ok(games
+            .values()
+            .find(|g| g.invite_code.as_deref() == Some(code))
+            .cloned())
+    }
+
+    async fn remove_last_action(
+        &self,
+        id: GameId,
+    ) -> AppResult<Option<(MinesweeperGame, Option<GameAction>)>> {
+        let mut removed = None;
+        let game = self.update_game(id, None, |game| {
+            removed = game.undo_last_action();
+        })?;
+        Ok(game.map(|game| (game, removed)))
+    }
+
+    async fn add_hint(&self, id: GameId) -> AppResult<Option<MinesweeperGame>> {
+        self.update_game(id, None, |game| {
+            game.hints_used += 1;
+        })
+    }
+
+    async fn mark_finished(
+        &self,
+        id: GameId,
+        finished_at: DateTime<Utc>,
+    ) -> AppResult<Option<MinesweeperGame>> {
+        self.update_game(id, None, |game| {
+            game.finished_at.get_or_insert(finished_at);
         })
     }
 }
 
 #[async_trait]
 impl UserGameRepository for InMemoryGameRepository {
-    async fn add_mapping(&self, user_id: &str, game_id: i32) -> AppResult<()> {
+    async fn add_mapping(&self, user_id: &UserId, game_id: GameId) -> AppResult<()> {
         let mut user_games = self
             .user_games
             .write()
             .map_err(|e| AppError::Internal(e.to_string()))?;
-        user_games
-            .entry(user_id.to_string())
-            .or_default()
-            .push(game_id);
+        user_games.entry(user_id.clone()).or_default().push(game_id);
         Ok(())
     }
 
-    async fn get_game_ids_by_user_id(&self, user_id: &str) -> AppResult<Vec<i32>> {
+    async fn get_game_ids_by_user_id(&self, user_id: &UserId) -> AppResult<Vec<GameId>> {
         let user_games = self
             .user_games
             .read()
             .map_err(|e| AppError::Internal(e.to_string()))?;
         Ok(user_games.get(user_id).cloned().unwrap_or_default())
     }
+}
 
-    async fn get_game_owner(&self, game_id: i32) -> AppResult<Option<String>> {
-        let user_games = self
-            .user_games
+#[async_trait]
+impl MatchRepository for InMemoryGameRepository {
+    async fn get_match(&self, id: MatchId) -> AppResult<Option<VersusMatch>> {
+        let matches = self
+            .matches
             .read()
             .map_err(|e| AppError::Internal(e.to_string()))?;
-        
-        for (user_id, games) in user_games.iter() {
-            if games.contains(&game_id) {
-                return Ok(Some(user_id.clone()));
-            }
+        Ok(matches.get(&id).cloned())
+    }
+
+    async fn insert_match(&self, versus: VersusMatch) -> AppResult<Option<VersusMatch>> {
+        let mut matches = self
+            .matches
+            .write()
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        if matches.contains_key(&versus.id) {
+            return Ok(None);
         }
-        Ok(None)
+        matches.insert(versus.id, versus.clone());
+        Ok(Some(versus))
+    }
+
+    async fn add_match_player(
+        &self,
+        id: MatchId,
+        user_id: &UserId,
+    ) -> AppResult<Option<VersusMatch>> {
+        self.update_match(id, |versus| {
+            if versus.status != MatchStatus::Waiting
+                || versus.has_player(user_id)
+                || versus.players.len() >= MAX_MATCH_PLAYERS
+            {
+                return false;
+            }
+            versus.players.push(MatchPlayer {
+                user_id: user_id.clone(),
+                game_id: None,
+            });
+            true
+        })
+    }
+
+    async fn start_match(
+        &self,
+        id: MatchId,
+        players: &[MatchPlayer],
+        started_at: DateTime<Utc>,
+    ) -> AppResult<Option<VersusMatch>> {
+        self.update_match(id, |versus| {
+            if versus.status != MatchStatus::Waiting {
+                return false;
+            }
+            versus.players = players.to_vec();
+            versus.status = MatchStatus::InProgress;
+            versus.started_at = Some(started_at);
+            true
+        })
+    }
+
+    async fn finish_match(
+        &self,
+        id: MatchId,
+        winner: Option<UserId>,
+        finished_at: DateTime<Utc>,
+    ) -> AppResult<Option<VersusMatch>> {
+        self.update_match(id, |versus| {
+            if versus.status != MatchStatus::InProgress {
+                return false;
+            }
+            versus.status = MatchStatus::Finished;
+            versus.winner = winner;
+            versus.finished_at = Some(finished_at);
+            true
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::model::BoardState;
-    use chrono::Utc;
+    use crate::model::{ActionKind, BoardState};
     use std::collections::HashSet;
 
     #[tokio::test]
     async fn test_in_memory_repo() {
         let repo = InMemoryGameRepository::new();
+        let id = GameId::try_from(123).unwrap();
         let game = MinesweeperGame {
-            id: 123,
+            id,
             board: vec![vec![BoardState::Zero; 10]; 10],
             moves: HashSet::new(),
             mine_points: HashSet::new(),
@@ -148,20 +362,75 @@ mod tests {
             cols: 10,
             rows: 10,
             mine_count_target: 10,
+            seed: None,
+            no_guess: false,
+            hints_used: 0,
+            practice: false,
+            started_at: None,
+            finished_at: None,
+            actions: Vec::new(),
+            match_id: None,
+            owner: None,
+            participants: Vec::new(),
+            invite_code: None,
+            private: false,
+            version: 0,
         };
 
-        repo.save(game.clone()).await.unwrap();
-        let retrieved = repo.get_game(123).await.unwrap().unwrap();
-        assert_eq!(retrieved.id, 123);
+        let saved = repo.save(game.clone()).await.unwrap();
+        assert_eq!(saved.version, 1);
+        let retrieved = repo.get_game(id).await.unwrap().unwrap();
+        assert_eq!(retrieved.id, id);
 
         let p = Point { x: 1, y: 1 };
-        let updated = repo.add_moves(123, &[p]).await.unwrap().unwrap();
+        let reveal = GameAction::new(ActionKind::Reveal, p, vec![p]);
+        let updated = repo.add_moves(id, 1, &[p], &reveal).await.unwrap().unwrap();
         assert!(updated.moves.contains(&p));
 
-        let updated = repo.add_flag(123, p).await.unwrap().unwrap();
+        let flag = GameAction::new(ActionKind::Flag, p, Vec::new());
+        let updated = repo.add_flag(id, 2, p, &flag).await.unwrap().unwrap();
         assert!(updated.flag_points.contains(&p));
 
-        let updated = repo.remove_flag(123, p).await.unwrap().unwrap();
+        let unflag = GameAction::new(ActionKind::Unflag, p, Vec::new());
+        let updated = repo.remove_flag(id, 3, p, &unflag).await.unwrap().unwrap();
         assert!(!updated.flag_points.contains(&p));
+        assert_eq!(updated.actions, vec![reveal, flag, unflag]);
+
+        let (updated, removed) = repo.remove_last_action(id).await.unwrap().unwrap();
+        assert!(updated.flag_points.contains(&p));
+        assert_eq!(removed.map(|a| a.kind), Some(ActionKind::Unflag));
+        repo.remove_last_action(id).await.unwrap();
+        let (updated, _) = repo.remove_last_action(id).await.unwrap().unwrap();
+        assert!(updated.moves.is_empty());
+        assert!(updated.actions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn stale_writes_are_rejected() {
+        let repo = InMemoryGameRepository::new();
+        let game = MinesweeperGame::new(5, 5, 3, &Default::default());
+        let stale = game.clone();
+        let saved = repo.save(game).await.unwrap();
+
+        assert!(matches!(repo.save(stale).await, Err(AppError::Conflict(_))));
+
+        let p = Point { x: 0, y: 0 };
+        let reveal = GameAction::new(ActionKind::Reveal, p, vec![p]);
+        let updated = repo
+            .add_moves(saved.id, saved.version, &[p], &reveal)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.version, saved.version + 1);
+
+        let flag = GameAction::new(ActionKind::Flag, p, Vec::new());
+        assert!(matches!(
+            repo.add_flag(saved.id, saved.version, p, &flag).await,
+            Err(AppError::Conflict(_))
+        ));
+        assert!(matches!(repo.save(saved).await, Err(AppError::Conflict(_))));
+        let stored = repo.get_game(updated.id).await.unwrap().unwrap();
+        assert_eq!(stored.version, updated.version);
+        assert!(stored.flag_points.is_empty());
     }
 }
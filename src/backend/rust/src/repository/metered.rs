@@ -0,0 +1,217 @@
+use crate::error::AppResult;
+use crate::model::{
+    GameAction, GameId, MatchId, MatchPlayer, MinesweeperGame, Point, UserId, VersusMatch,
+};
+use crate::repository::{
+    GameRepository, MatchRepository, MinesweeperRepository, UserGameRepository,
+};
+use crate::settings::DatabaseBackend;
+use crate::telemetry::metrics::MinesweeperMetrics;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Wraps any repository to record the latency of every call, labelled by backend and
+/// operation.
+pub struct MeteredRepository {
+    inner: Arc<dyn MinesweeperRepository>,
+    backend: DatabaseBackend,
+}
+
+impl MeteredRepository {
+    pub fn new(inner: Arc<dyn MinesweeperRepository>, backend: DatabaseBackend) -> Self {
+        MeteredRepository { inner, backend }
+    }
+
+    async fn timed<T>(
+        &self,
+        operation: &'static str,
+        call: impl Future<Output = AppResult<T>>,
+    ) -> AppResult<T> {
+        let started = Instant::now();
+        let result = call.await;
+        MinesweeperMetrics::record_repository_call(
+            self.backend,
+            operation,
+            result.is_ok(),
+            started.elapsed(),
+        );
+        result
+    }
+}
+
+#[async_trait]
+impl GameRepository for MeteredRepository {
+    async fn ping(&self) -> AppResult<()> {
+        self.timed("ping", self.inner.ping()).await
+    }
+
+    async fn get_game(&self, id: GameId) -> AppResult<Option<MinesweeperGame>> {
+        self.timed("get_game", self.inner.get_game(id)).await
+    }
+
+    async fn get_games_by_ids(&self, ids: &[GameId]) -> AppResult<Vec<MinesweeperGame>> {
+        self.timed("get_games_by_ids", self.inner.get_games_by_ids(ids))
+            .await
+    }
+
+    async fn save(&self, game: MinesweeperGame) -> AppResult<MinesweeperGame> {
+        self.timed("save", self.inner.save(game)).await
+    }
+
+    async fn insert(&self, game: MinesweeperGame) -> AppResult<Option<MinesweeperGame>> {
+        self.timed("insert", self.inner.insert(game)).await
+    }
+
+    async fn add_moves(
+        &self,
+        id: GameId,
+        version: i64,
+        points: &[Point],
+        action: &GameAction,
+    ) -> AppResult<Option<MinesweeperGame>> {
+        self.timed(
+            "add_moves",
+            self.inner.add_moves(id, version, points, action),
+        )
+        .await
+    }
+
+    async fn add_flag(
+        &self,
+        id: GameId,
+        version: i64,
+        point: Point,
+        action: &GameAction,
+    ) -> AppResult<Option<MinesweeperGame>> {
+        self.timed("add_flag", self.inner.add_flag(id, version, point, action))
+            .await
+    }
+
+    async fn remove_flag(
+        &self,
+        id: GameId,
+        version: i64,
+        point: Point,
+        action: &GameAction,
+    ) -> AppResult<Option<MinesweeperGame>> {
+        self.timed(
+            "remove_flag",
+            self.inner.remove_flag(id, version, point, action),
+        )
+        .await
+    }
+
+    async fn add_participant(
+        &self,
+        id: GameId,
+        user_id: &UserId,
+    ) -> AppResult<Option<MinesweeperGame>> {
+        self.timed("add_participant", self.inner.add_participant(id, user_id))
+            .await
+    }
+
+    async fn set_invite_code(&self, id: GameId, code: &str) -> AppResult<Option<MinesweeperGame>> {
+        self.timed("set_invite_code", self.inner.set_invite_code(id, code))
+            .await
+    }
+
+    async fn set_private(&self, id: GameId, private: bool) -> AppResult<Option<MinesweeperGame>> {
+        self.timed("set_private", self.inner.set_private(id, private))
+            .await
+    }
+
+    async fn get_game_by_invite_code(&self, code: &str) -> AppResult<Option<MinesweeperGame>> {
+        self.timed(
+            "get_game_by_invite_code",
+            self.inner.get_game_by_invite_code(code),
+        )
+        .await
+    }
+
+    async fn remove_last_action(
+        &self,
+        id: GameId,
+    ) -> AppResult<Option<(MinesweeperGame, Option<GameAction>)>> {
+        self.timed("remove_last_action", self.inner.remove_last_action(id))
+            .await
+    }
+
+    async fn add_hint(&self, id: GameId) -> AppResult<Option<MinesweeperGame>> {
+        self.timed("add_hint", self.inner.add_hint(id)).await
+    }
+
+    async fn mark_finished(
+        &self,
+        id: GameId,
+        finished_at: DateTime<Utc>,
+    ) -> AppResult<Option<MinesweeperGame>> {
+        self.timed("mark_finished", self.inner.mark_finished(id, finished_at))
+            .await
+    }
+}
+
+#[async_trait]
+impl UserGameRepository for MeteredRepository {
+    async fn add_mapping(&self, user_id: &UserId, game_id: GameId) -> AppResult<()> {
+        self.timed("add_mapping", self.inner.add_mapping(user_id, game_id))
+            .await
+    }
+
+    async fn get_game_ids_by_user_id(&self, user_id: &UserId) -> AppResult<Vec<GameId>> {
+        self.timed(
+            "get_game_ids_by_user_id",
+            self.inner.get_game_ids_by_user_id(user_id),
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl MatchRepository for MeteredRepository {
+    async fn get_match(&self, id: MatchId) -> AppResult<Option<VersusMatch>> {
+        self.timed("get_match", self.inner.get_match(id)).await
+    }
+
+    async fn insert_match(&self, versus: VersusMatch) -> AppResult<Option<VersusMatch>> {
+        self.timed("insert_match", self.inner.insert_match(versus))
+            .await
+    }
+
+    async fn add_match_player(
+        &self,
+        id: MatchId,
+        user_id: &UserId,
+    ) -> AppResult<Option<VersusMatch>> {
+        self.timed("add_match_player", self.inner.add_match_player(id, user_id))
+            .await
+    }
+
+    async fn start_match(
+        &self,
+        id: MatchId,
+        players: &[MatchPlayer],
+        started_at: DateTime<Utc>,
+    ) -> AppResult<Option<VersusMatch>> {
+        self.timed(
+            "start_match",
+            self.inner.start_match(id, players, started_at),
+        )
+        .await
+    }
+
+    async fn finish_match(
+        &self,
+        id: MatchId,
+        winner: Option<UserId>,
+        finished_at: DateTime<Utc>,
+    ) -> AppResult<Option<VersusMatch>> {
+        self.timed(
+            "finish_match",
+            self.inner.finish_match(id, winner, finished_at),
+        )
+        .await
+    }
+}
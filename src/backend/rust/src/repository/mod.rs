@@ -1,52 +1,213 @@
 pub mod memory;
+pub mod metered;
 pub mod mongo;
+pub mod postgres;
+mod sql;
+pub mod sqlite;
 
 use crate::error::AppResult;
-use crate::model::{MinesweeperGame, Point};
+use crate::model::{
+    GameAction, GameId, MatchId, MatchPlayer, MinesweeperGame, Point, RepositoryStatus, UserId,
+    VersusMatch,
+};
+use crate::settings::{DatabaseBackend, DatabaseSettings};
+use anyhow::{anyhow, Context};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use std::sync::Arc;
+use std::time::Duration;
 
 pub use memory::InMemoryGameRepository;
+pub use metered::MeteredRepository;
 pub use mongo::MongoGameRepository;
+pub use postgres::PostgresGameRepository;
+pub use sqlite::SqliteGameRepository;
 
+/// Every write bumps the game's `version`. `save` and the board changes only apply on
+/// top of the version they were computed from and fail with `AppError::Conflict` when
+/// another write got there first.
 #[async_trait]
 pub trait GameRepository: Send + Sync {
-    async fn get_game(&self, id: i32) -> AppResult<Option<MinesweeperGame>>;
-    async fn get_games_by_ids(&self, ids: &[i32]) -> AppResult<Vec<MinesweeperGame>>;
-    async fn save(&self, game: MinesweeperGame) -> AppResult<()>;
-    async fn add_moves(&self, id: i32, points: &[Point]) -> AppResult<Option<MinesweeperGame>>;
-    async fn add_flag(&self, id: i32, point: Point) -> AppResult<Option<MinesweeperGame>>;
-    async fn remove_flag(&self, id: i32, point: Point) -> AppResult<Option<MinesweeperGame>>;
+    /// Round-trips to the backing store, failing if it cannot be reached.
+    async fn ping(&self) -> AppResult<()>;
+    async fn get_game(&self, id: GameId) -> AppResult<Option<MinesweeperGame>>;
+    async fn get_games_by_ids(&self, ids: &[GameId]) -> AppResult<Vec<MinesweeperGame>>;
+    /// Inserts a new game or replaces the stored one, returning it with its new version.
+    async fn save(&self, game: MinesweeperGame) -> AppResult<MinesweeperGame>;
+    /// Stores a new game, returning `None` and leaving the stored game untouched when its
+    /// id is already taken.
+    async fn insert(&self, game: MinesweeperGame) -> AppResult<Option<MinesweeperGame>>;
+    async fn add_moves(
+        &self,
+        id: GameId,
+        version: i64,
+        points: &[Point],
+        action: &GameAction,
+    ) -> AppResult<Option<MinesweeperGame>>;
+    async fn add_flag(
+        &self,
+        id: GameId,
+        version: i64,
+        point: Point,
+        action: &GameAction,
+    ) -> AppResult<Option<MinesweeperGame>>;
+    async fn remove_flag(
+        &self,
+        id: GameId,
+        version: i64,
+        point: Point,
+        action: &GameAction,
+    ) -> AppResult<Option<MinesweeperGame>>;
+    async fn add_participant(
+        &self,
+        id: GameId,
+        user_id: &UserId,
+    ) -> AppResult<Option<MinesweeperGame>>;
+    /// Fails with `Conflict` when another game already holds `code`.
+    async fn set_invite_code(&self, id: GameId, code: &str) -> AppResult<Option<MinesweeperGame>>;
+    async fn set_private(&self, id: GameId, private: bool) -> AppResult<Option<MinesweeperGame>>;
+    async fn get_game_by_invite_code(&self, code: &str) -> AppResult<Option<MinesweeperGame>>;
+    /// Atomically reverts the most recent entry of the action log, returning the game as
+    /// it is afterwards along with the action that was removed, if the log had any.
+    async fn remove_last_action(
+        &self,
+        id: GameId,
+    ) -> AppResult<Option<(MinesweeperGame, Option<GameAction>)>>;
+    async fn add_hint(&self, id: GameId) -> AppResult<Option<MinesweeperGame>>;
+    async fn mark_finished(
+        &self,
+        id: GameId,
+        finished_at: DateTime<Utc>,
+    ) -> AppResult<Option<MinesweeperGame>>;
 }
 
 #[async_trait]
 pub trait UserGameRepository: Send + Sync {
-    async fn add_mapping(&self, user_id: &str, game_id: i32) -> AppResult<()>;
-    async fn get_game_ids_by_user_id(&self, user_id: &str) -> AppResult<Vec<i32>>;
-    async fn get_game_owner(&self, game_id: i32) -> AppResult<Option<String>>;
+    async fn add_mapping(&self, user_id: &UserId, game_id: GameId) -> AppResult<()>;
+    async fn get_game_ids_by_user_id(&self, user_id: &UserId) -> AppResult<Vec<GameId>>;
 }
 
-pub trait MinesweeperRepository: GameRepository + UserGameRepository {}
-impl<T: GameRepository + UserGameRepository> MinesweeperRepository for T {}
+/// State transitions return `None` when the match is missing or no longer in the state
+/// the transition starts from.
+#[async_trait]
+pub trait MatchRepository: Send + Sync {
+    async fn get_match(&self, id: MatchId) -> AppResult<Option<VersusMatch>>;
+    /// Stores a new match, returning `None` and leaving the stored match untouched when
+    /// its id is already taken.
+    async fn insert_match(&self, versus: VersusMatch) -> AppResult<Option<VersusMatch>>;
+    async fn add_match_player(
+        &self,
+        id: MatchId,
+        user_id: &UserId,
+    ) -> AppResult<Option<VersusMatch>>;
+    async fn start_match(
+        &self,
+        id: MatchId,
+        players: &[MatchPlayer],
+        started_at: DateTime<Utc>,
+    ) -> AppResult<Option<VersusMatch>>;
+    async fn finish_match(
+        &self,
+        id: MatchId,
+        winner: Option<UserId>,
+        finished_at: DateTime<Utc>,
+    ) -> AppResult<Option<VersusMatch>>;
+}
+
+pub trait MinesweeperRepository: GameRepository + UserGameRepository + MatchRepository {}
+impl<T: GameRepository + UserGameRepository + MatchRepository> MinesweeperRepository for T {}
 
 pub async fn init_repository(
-    settings: &crate::settings::DatabaseSettings,
-) -> Arc<dyn MinesweeperRepository> {
-    if let Some(ref addr) = settings.addr {
-        let mongo_uri = format!("mongodb://{}", addr);
-        tracing::info!("Using MongoDB at {}", mongo_uri);
-        match MongoGameRepository::new(&mongo_uri, &settings.name).await {
-            Ok(r) => Arc::new(r),
-            Err(e) => {
-                tracing::error!(
-                    "Failed to connect to MongoDB: {}, falling back to In-Memory",
-                    e
+    settings: &DatabaseSettings,
+) -> anyhow::Result<(Arc<dyn MinesweeperRepository>, RepositoryStatus)> {
+    let backend = settings.backend();
+    tracing::info!("Using {} repository", backend);
+
+    let timeout = Duration::from_secs(settings.startup_timeout_secs);
+    let result = tokio::time::timeout(timeout, connect_with_retry(settings, backend))
+        .await
+        .unwrap_or_else(|_| Err(anyhow!("timed out after {}s", timeout.as_secs())));
+
+    let (repo, status): (Arc<dyn MinesweeperRepository>, _) = match result {
+        Ok(repo) => (repo, RepositoryStatus::healthy(backend)),
+        Err(e) if settings.strict => {
+            return Err(e.context(format!("Failed to initialise {} repository", backend)));
+        }
+        Err(e) => {
+            tracing::error!(
+                "Failed to initialise {} repository: {:#}, falling back to In-Memory; games will not be persisted",
+                backend,
+                e
+            );
+            (
+                Arc::new(InMemoryGameRepository::new()),
+                RepositoryStatus::fallback(backend, format!("{:#}", e)),
+            )
+        }
+    };
+    Ok((
+        Arc::new(MeteredRepository::new(repo, status.active)),
+        status,
+    ))
+}
+
+async fn connect_with_retry(
+    settings: &DatabaseSettings,
+    backend: DatabaseBackend,
+) -> anyhow::Result<Arc<dyn MinesweeperRepository>> {
+    let mut backoff = Duration::from_millis(settings.retry_backoff_ms);
+    let mut attempt = 1;
+    loop {
+        match connect(settings, backend).await {
+            Ok(repo) => return Ok(repo),
+            Err(e) if attempt < settings.connect_attempts => {
+                tracing::warn!(
+                    "Connecting to {} failed (attempt {}/{}): {:#}, retrying in {:?}",
+                    backend,
+                    attempt,
+                    settings.connect_attempts,
+                    e,
+                    backoff
                 );
-                Arc::new(InMemoryGameRepository::new())
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+                attempt += 1;
             }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+async fn connect(
+    settings: &DatabaseSettings,
+    backend: DatabaseBackend,
+) -> anyhow::Result<Arc<dyn MinesweeperRepository>> {
+    match backend {
+        DatabaseBackend::Memory => Ok(Arc::new(InMemoryGameRepository::new())),
+        DatabaseBackend::Mongo => {
+            let addr = settings
+                .addr
+                .as_ref()
+                .context("database.addr is required for the mongo backend")?;
+            let repo =
+                MongoGameRepository::new(&format!("mongodb://{}", addr), &settings.name).await?;
+            repo.ping().await.map_err(|e| anyhow!(e.to_string()))?;
+            repo.migrate().await?;
+            Ok(Arc::new(repo))
+        }
+        DatabaseBackend::Postgres => {
+            let url = settings
+                .postgres_url
+                .as_ref()
+                .context("database.postgres_url is required for the postgres backend")?;
+            Ok(Arc::new(PostgresGameRepository::new(url).await?))
+        }
+        DatabaseBackend::Sqlite => {
+            let path = settings
+                .sqlite_path
+                .as_ref()
+                .context("database.sqlite_path is required for the sqlite backend")?;
+            Ok(Arc::new(SqliteGameRepository::new(path).await?))
         }
-    } else {
-        tracing::info!("Using In-Memory Repository");
-        Arc::new(InMemoryGameRepository::new())
     }
 }
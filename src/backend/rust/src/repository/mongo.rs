@@ -1,60 +1,238 @@
-use crate::error::AppResult;
-use crate::model::{MinesweeperGame, Point};
-use crate::repository::{GameRepository, UserGameRepository};
+use crate::error::{AppError, AppResult};
+use crate::model::{
+    ActionKind, GameAction, GameId, MatchId, MatchPlayer, MatchStatus, MinesweeperGame, Point,
+    UserId, VersusMatch, MAX_MATCH_PLAYERS,
+};
+use crate::repository::{GameRepository, MatchRepository, UserGameRepository};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use mongodb::{
-    bson::doc, options::FindOneAndUpdateOptions, options::ReturnDocument, Client, Collection,
+    bson::{doc, Bson, Document},
+    error::{ErrorKind, WriteFailure},
+    options::{ClientOptions, FindOneAndUpdateOptions, IndexOptions, ReturnDocument},
+    Client, Collection, Database, IndexModel,
 };
+use std::time::Duration;
 use tracing::instrument;
 
+const UNDO_MAX_ATTEMPTS: usize = 5;
+const DUPLICATE_KEY: i32 = 11000;
+const NAMESPACE_NOT_FOUND: i32 = 26;
+const INDEX_NOT_FOUND: i32 = 27;
+/// Replaced by `INVITE_CODE_INDEX`, which unlike it enforces uniqueness.
+const LEGACY_INVITE_CODE_INDEX: &str = "InviteCode_1";
+const INVITE_CODE_INDEX: &str = "InviteCode_unique";
+/// Fail operations against an unreachable server well before the driver's 30s default.
+const SERVER_SELECTION_TIMEOUT: Duration = Duration::from_secs(5);
+/// Data migrations are recorded here once applied, like sqlx's `_sqlx_migrations`.
+const MIGRATIONS: &str = "Migrations";
+const OWNER_BACKFILL: &str = "0001_game_owner";
+
 pub struct MongoGameRepository {
+    db: Database,
     collection: Collection<MinesweeperGame>,
     user_games_collection: Collection<UserGameMapping>,
+    matches_collection: Collection<VersusMatch>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize)]
 struct UserGameMapping {
     #[serde(rename = "_id")]
-    user_id: String,
-    game_ids: Vec<i32>,
+    user_id: UserId,
+    game_ids: Vec<GameId>,
 }
 
 impl MongoGameRepository {
     pub async fn new(uri: &str, database: &str) -> mongodb::error::Result<Self> {
-        let client = Client::with_uri_str(uri).await?;
+        let mut options = ClientOptions::parse(uri).await?;
+        options.server_selection_timeout = Some(SERVER_SELECTION_TIMEOUT);
+        let client = Client::with_options(options)?;
         let db = client.database(database);
         let collection = db.collection::<MinesweeperGame>("Games");
         let user_games_collection = db.collection::<UserGameMapping>("UserGames");
+        let matches_collection = db.collection::<VersusMatch>("Matches");
         Ok(MongoGameRepository {
+            db,
             collection,
             user_games_collection,
+            matches_collection,
         })
     }
 
+    /// Creates the indexes behind every lookup not made by `_id` and applies pending data
+    /// migrations. Both are idempotent, so this runs on every startup.
+    pub async fn migrate(&self) -> mongodb::error::Result<()> {
+        match self
+            .collection
+            .drop_index(LEGACY_INVITE_CODE_INDEX, None)
+            .await
+        {
+            Err(e) if is_command_error(&e, &[NAMESPACE_NOT_FOUND, INDEX_NOT_FOUND]) => {}
+            result => result?,
+        }
+        // Games without a code store null, which a sparse index would still cover
+        let options = IndexOptions::builder()
+            .name(INVITE_CODE_INDEX.to_string())
+            .unique(true)
+            .partial_filter_expression(doc! { "InviteCode": { "$type": "string" } })
+            .build();
+        self.collection
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "InviteCode": 1 })
+                    .options(options)
+                    .build(),
+                None,
+            )
+            .await?;
+
+        let migrations = self.db.collection::<Document>(MIGRATIONS);
+        if migrations
+            .find_one(doc! { "_id": OWNER_BACKFILL }, None)
+            .await?
+            .is_none()
+        {
+            self.backfill_owners().await?;
+            let applied =
+                doc! { "_id": OWNER_BACKFILL, "AppliedAt": mongodb::bson::DateTime::now() };
+            match migrations.insert_one(applied, None).await {
+                // Another instance finished the same migration first
+                Err(e) if is_duplicate_key(&e) => {}
+                result => {
+                    result?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Copies each game's owner from `UserGames` onto games stored before it was kept there.
+    async fn backfill_owners(&self) -> mongodb::error::Result<()> {
+        use futures_util::TryStreamExt;
+        let mut mappings = self.user_games_collection.find(None, None).await?;
+        while let Some(mapping) = mappings.try_next().await? {
+            let ids: Vec<Bson> = mapping.game_ids.into_iter().map(Bson::from).collect();
+            self.collection
+                .update_many(
+                    doc! { "_id": { "$in": ids }, "Owner": null },
+                    doc! { "$set": { "Owner": mapping.user_id } },
+                    None,
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Applies `update` and bumps the version, provided the game is still at `expected`
+    /// when one is given.
     async fn update_game(
         &self,
-        id: i32,
-        update: mongodb::bson::Document,
+        id: GameId,
+        expected: Option<i64>,
+        mut update: Document,
     ) -> AppResult<Option<MinesweeperGame>> {
+        match update.get_document_mut("$inc") {
+            Ok(inc) => {
+                inc.insert("Version", 1_i64);
+            }
+            Err(_) => {
+                update.insert("$inc", doc! { "Version": 1_i64 });
+            }
+        }
+        let filter = match expected {
+            Some(version) => version_filter(id, version),
+            None => doc! { "_id": id },
+        };
+
         let options = FindOneAndUpdateOptions::builder()
             .return_document(ReturnDocument::After)
             .build();
-        Ok(self
+        let updated = match self
             .collection
-            .find_one_and_update(doc! { "_id": id }, update, options)
+            .find_one_and_update(filter, update, options)
+            .await
+        {
+            Ok(updated) => updated,
+            // The invite code is the only unique field an update can collide on
+            Err(e) if is_duplicate_key(&e) => return Err(AppError::Conflict(e.to_string())),
+            Err(e) => return Err(e.into()),
+        };
+
+        if updated.is_none() && expected.is_some() && self.get_game(id).await?.is_some() {
+            return Err(AppError::Conflict(id.to_string()));
+        }
+        Ok(updated)
+    }
+
+    async fn update_match(
+        &self,
+        filter: mongodb::bson::Document,
+        update: mongodb::bson::Document,
+    ) -> AppResult<Option<VersusMatch>> {
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        Ok(self
+            .matches_collection
+            .find_one_and_update(filter, update, options)
             .await?)
     }
 }
 
+impl From<GameId> for Bson {
+    fn from(id: GameId) -> Self {
+        Bson::Int32(id.get())
+    }
+}
+
+impl From<MatchId> for Bson {
+    fn from(id: MatchId) -> Self {
+        Bson::Int32(id.get())
+    }
+}
+
+impl From<UserId> for Bson {
+    fn from(id: UserId) -> Self {
+        Bson::String(id.as_str().to_string())
+    }
+}
+
+/// Games stored before versioning have no `Version` field and count as version 0.
+fn version_filter(id: GameId, version: i64) -> Document {
+    if version == 0 {
+        doc! { "_id": id, "Version": { "$in": [0_i64, null] } }
+    } else {
+        doc! { "_id": id, "Version": version }
+    }
+}
+
+fn is_duplicate_key(e: &mongodb::error::Error) -> bool {
+    matches!(
+        e.kind.as_ref(),
+        ErrorKind::Write(WriteFailure::WriteError(we)) if we.code == DUPLICATE_KEY
+    ) || is_command_error(e, &[DUPLICATE_KEY])
+}
+
+/// Commands such as `findAndModify` report failures as command errors, not write errors.
+fn is_command_error(e: &mongodb::error::Error, codes: &[i32]) -> bool {
+    matches!(e.kind.as_ref(), ErrorKind::Command(ce) if codes.contains(&ce.code))
+}
+
 #[async_trait]
 impl GameRepository for MongoGameRepository {
+    /// The client connects lazily, so this is what actually proves the server is reachable.
+    async fn ping(&self) -> AppResult<()> {
+        self.db.run_command(doc! { "ping": 1 }, None).await?;
+        Ok(())
+    }
+
     #[instrument(skip(self))]
-    async fn get_game(&self, id: i32) -> AppResult<Option<MinesweeperGame>> {
+    async fn get_game(&self, id: GameId) -> AppResult<Option<MinesweeperGame>> {
         Ok(self.collection.find_one(doc! { "_id": id }, None).await?)
     }
 
     #[instrument(skip(self))]
-    async fn get_games_by_ids(&self, ids: &[i32]) -> AppResult<Vec<MinesweeperGame>> {
+    async fn get_games_by_ids(&self, ids: &[GameId]) -> AppResult<Vec<MinesweeperGame>> {
         use futures_util::TryStreamExt;
         let ids_bson = mongodb::bson::to_bson(ids)?;
         let cursor = self
@@ -65,64 +243,304 @@ impl GameRepository for MongoGameRepository {
     }
 
     #[instrument(skip(self, game))]
-    async fn save(&self, game: MinesweeperGame) -> AppResult<()> {
+    async fn save(&self, mut game: MinesweeperGame) -> AppResult<MinesweeperGame> {
+        let filter = version_filter(game.id, game.version);
+        game.version += 1;
         let options = mongodb::options::ReplaceOptions::builder()
             .upsert(true)
             .build();
-        self.collection
-            .replace_one(doc! { "_id": game.id }, game, options)
-            .await?;
-        Ok(())
+        match self.collection.replace_one(filter, &game, options).await {
+            Ok(_) => Ok(game),
+            // A stored game at another version fails the filter, so the upsert collides on _id
+            Err(e) if is_duplicate_key(&e) => Err(AppError::Conflict(game.id.to_string())),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    #[instrument(skip(self, game))]
+    async fn insert(&self, mut game: MinesweeperGame) -> AppResult<Option<MinesweeperGame>> {
+        game.version = 1;
+        match self.collection.insert_one(&game, None).await {
+            Ok(_) => Ok(Some(game)),
+            Err(e) if is_duplicate_key(&e) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
     }
 
-    #[instrument(skip(self, points))]
-    async fn add_moves(&self, id: i32, points: &[Point]) -> AppResult<Option<MinesweeperGame>> {
+    #[instrument(skip(self, points, action))]
+    async fn add_moves(
+        &self,
+        id: GameId,
+        version: i64,
+        points: &[Point],
+        action: &GameAction,
+    ) -> AppResult<Option<MinesweeperGame>> {
         let points_bson = mongodb::bson::to_bson(points)?;
-        let update = doc! { "$addToSet": { "Moves": { "$each": points_bson } } };
-        self.update_game(id, update).await
+        let action_bson = mongodb::bson::to_bson(action)?;
+        let update = doc! {
+            "$addToSet": { "Moves": { "$each": points_bson } },
+            "$push": { "Actions": action_bson },
+        };
+        self.update_game(id, Some(version), update).await
     }
 
-    #[instrument(skip(self))]
-    async fn add_flag(&self, id: i32, point: Point) -> AppResult<Option<MinesweeperGame>> {
+    #[instrument(skip(self, action))]
+    async fn add_flag(
+        &self,
+        id: GameId,
+        version: i64,
+        point: Point,
+        action: &GameAction,
+    ) -> AppResult<Option<MinesweeperGame>> {
         let point_bson = mongodb::bson::to_bson(&point)?;
-        let update = doc! { "$addToSet": { "FlagPoints": point_bson } };
-        self.update_game(id, update).await
+        let action_bson = mongodb::bson::to_bson(action)?;
+        let update = doc! {
+            "$addToSet": { "FlagPoints": point_bson },
+            "$push": { "Actions": action_bson },
+        };
+        self.update_game(id, Some(version), update).await
     }
 
-    #[instrument(skip(self))]
-    async fn remove_flag(&self, id: i32, point: Point) -> AppResult<Option<MinesweeperGame>> {
+    #[instrument(skip(self, action))]
+    async fn remove_flag(
+        &self,
+        id: GameId,
+        version: i64,
+        point: Point,
+        action: &GameAction,
+    ) -> AppResult<Option<MinesweeperGame>> {
         let point_bson = mongodb::bson::to_bson(&point)?;
-        let update = doc! { "$pull": { "FlagPoints": point_bson } };
-        self.update_game(id, update).await
+        let action_bson = mongodb::bson::to_bson(action)?;
+        let update = doc! {
+            "$pull": { "FlagPoints": point_bson },
+            "$push": { "Actions": action_bson },
+        };
+        self.update_game(id, Some(version), update).await
+    }
+
+    #[instrument(skip(self))]
+    async fn add_participant(
+        &self,
+        id: GameId,
+        user_id: &UserId,
+    ) -> AppResult<Option<MinesweeperGame>> {
+        let update = doc! { "$addToSet": { "Participants": user_id.as_str() } };
+        self.update_game(id, None, update).await
+    }
+
+    #[instrument(skip(self))]
+    async fn set_invite_code(&self, id: GameId, code: &str) -> AppResult<Option<MinesweeperGame>> {
+        let update = doc! { "$set": { "InviteCode": code } };
+        self.update_game(id, None, update).await
+    }
+
+    #[instrument(skip(self))]
+    async fn set_private(&self, id: GameId, private: bool) -> AppResult<Option<MinesweeperGame>> {
+        let update = doc! { "$set": { "Private": private } };
+        self.update_game(id, None, update).await
+    }
+
+    #[instrument(skip(self))]
+    async fn get_game_by_invite_code(&self, code: &str) -> AppResult<Option<MinesweeperGame>> {
+        Ok(self
+            .collection
+            .find_one(doc! { "InviteCode": code }, None)
+            .await?)
+    }
+
+    #[instrument(skip(self))]
+    async fn remove_last_action(
+        &self,
+        id: GameId,
+    ) -> AppResult<Option<(MinesweeperGame, Option<GameAction>)>> {
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+
+        for _ in 0..UNDO_MAX_ATTEMPTS {
+            let Some(game) = self.get_game(id).await? else {
+                return Ok(None);
+            };
+            let Some(action) = game.actions.last().cloned() else {
+                return Ok(Some((game, None)));
+            };
+
+            let point_bson = mongodb::bson::to_bson(&action.point)?;
+            let mut update = doc! {
+                "$pop": { "Actions": 1 },
+                "$unset": { "FinishedAt": "" },
+                "$inc": { "Version": 1_i64 },
+            };
+            match action.kind {
+                ActionKind::Reveal | ActionKind::Chord => {
+                    let revealed_bson = mongodb::bson::to_bson(&action.revealed)?;
+                    update.insert("$pullAll", doc! { "Moves": revealed_bson });
+                }
+                ActionKind::Flag => {
+                    update.insert("$pull", doc! { "FlagPoints": point_bson });
+                }
+                ActionKind::Unflag => {
+                    update.insert("$addToSet", doc! { "FlagPoints": point_bson });
+                }
+            }
+
+            // The version guards against another write landing between read and write
+            let filter = version_filter(id, game.version);
+            let updated = self
+                .collection
+                .find_one_and_update(filter, update, options.clone())
+                .await?;
+            if let Some(updated) = updated {
+                return Ok(Some((updated, Some(action))));
+            }
+        }
+
+        Err(AppError::Conflict(format!(
+            "Game {} changed concurrently while undoing",
+            id
+        )))
+    }
+
+    #[instrument(skip(self))]
+    async fn add_hint(&self, id: GameId) -> AppResult<Option<MinesweeperGame>> {
+        let update = doc! { "$inc": { "HintsUsed": 1 } };
+        self.update_game(id, None, update).await
+    }
+
+    #[instrument(skip(self))]
+    async fn mark_finished(
+        &self,
+        id: GameId,
+        finished_at: DateTime<Utc>,
+    ) -> AppResult<Option<MinesweeperGame>> {
+        let finished_at_bson = mongodb::bson::to_bson(&finished_at)?;
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        // Only the first transition to game over sets the timestamp
+        let updated = self
+            .collection
+            .find_one_and_update(
+                doc! { "_id": id, "FinishedAt": null },
+                doc! {
+                    "$set": { "FinishedAt": finished_at_bson },
+                    "$inc": { "Version": 1_i64 },
+                },
+                options,
+            )
+            .await?;
+
+        match updated {
+            Some(game) => Ok(Some(game)),
+            None => self.get_game(id).await,
+        }
     }
 }
 
 #[async_trait]
 impl UserGameRepository for MongoGameRepository {
-    async fn add_mapping(&self, user_id: &str, game_id: i32) -> AppResult<()> {
+    async fn add_mapping(&self, user_id: &UserId, game_id: GameId) -> AppResult<()> {
         let update = doc! { "$addToSet": { "game_ids": game_id } };
         let options = mongodb::options::FindOneAndUpdateOptions::builder()
             .upsert(true)
             .build();
         self.user_games_collection
-            .find_one_and_update(doc! { "_id": user_id }, update, options)
+            .find_one_and_update(doc! { "_id": user_id.as_str() }, update, options)
             .await?;
         Ok(())
     }
 
-    async fn get_game_ids_by_user_id(&self, user_id: &str) -> AppResult<Vec<i32>> {
+    async fn get_game_ids_by_user_id(&self, user_id: &UserId) -> AppResult<Vec<GameId>> {
         let mapping = self
             .user_games_collection
-            .find_one(doc! { "_id": user_id }, None)
+            .find_one(doc! { "_id": user_id.as_str() }, None)
             .await?;
         Ok(mapping.map(|m| m.game_ids).unwrap_or_default())
     }
+}
 
-    async fn get_game_owner(&self, game_id: i32) -> AppResult<Option<String>> {
-        let mapping = self
-            .user_games_collection
-            .find_one(doc! { "game_ids": game_id }, None)
-            .await?;
-        Ok(mapping.map(|m| m.user_id))
+#[async_trait]
+impl MatchRepository for MongoGameRepository {
+    #[instrument(skip(self))]
+    async fn get_match(&self, id: MatchId) -> AppResult<Option<VersusMatch>> {
+        Ok(self
+            .matches_collection
+            .find_one(doc! { "_id": id }, None)
+            .await?)
+    }
+
+    #[instrument(skip(self, versus))]
+    async fn insert_match(&self, versus: VersusMatch) -> AppResult<Option<VersusMatch>> {
+        match self.matches_collection.insert_one(&versus, None).await {
+            Ok(_) => Ok(Some(versus)),
+            Err(e) if is_duplicate_key(&e) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn add_match_player(
+        &self,
+        id: MatchId,
+        user_id: &UserId,
+    ) -> AppResult<Option<VersusMatch>> {
+        let waiting = mongodb::bson::to_bson(&MatchStatus::Waiting)?;
+        let player = mongodb::bson::to_bson(&MatchPlayer {
+            user_id: user_id.clone(),
+            game_id: None,
+        })?;
+        // Capacity, duplicate and state checks live in the filter so joins stay atomic
+        let last_slot = format!("Players.{}", MAX_MATCH_PLAYERS - 1);
+        let filter = doc! {
+            "_id": id,
+            "Status": waiting,
+            "Players.UserId": { "$ne": user_id.as_str() },
+            last_slot: { "$exists": false },
+        };
+        let update = doc! { "$push": { "Players": player } };
+        self.update_match(filter, update).await
+    }
+
+    #[instrument(skip(self, players))]
+    async fn start_match(
+        &self,
+        id: MatchId,
+        players: &[MatchPlayer],
+        started_at: DateTime<Utc>,
+    ) -> AppResult<Option<VersusMatch>> {
+        let waiting = mongodb::bson::to_bson(&MatchStatus::Waiting)?;
+        let in_progress = mongodb::bson::to_bson(&MatchStatus::InProgress)?;
+        let players_bson = mongodb::bson::to_bson(players)?;
+        let started_at_bson = mongodb::bson::to_bson(&started_at)?;
+        let filter = doc! { "_id": id, "Status": waiting };
+        let update = doc! {
+            "$set": {
+                "Players": players_bson,
+                "Status": in_progress,
+                "StartedAt": started_at_bson,
+            }
+        };
+        self.update_match(filter, update).await
+    }
+
+    #[instrument(skip(self))]
+    async fn finish_match(
+        &self,
+        id: MatchId,
+        winner: Option<UserId>,
+        finished_at: DateTime<Utc>,
+    ) -> AppResult<Option<VersusMatch>> {
+        let in_progress = mongodb::bson::to_bson(&MatchStatus::InProgress)?;
+        let finished = mongodb::bson::to_bson(&MatchStatus::Finished)?;
+        let finished_at_bson = mongodb::bson::to_bson(&finished_at)?;
+        let filter = doc! { "_id": id, "Status": in_progress };
+        let update = doc! {
+            "$set": {
+                "Status": finished,
+                "Winner": winner,
+                "FinishedAt": finished_at_bson,
+            }
+        };
+        self.update_match(filter, update).await
     }
 }
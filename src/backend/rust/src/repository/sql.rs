@@ -0,0 +1,136 @@
+use crate::error::{AppError, AppResult};
+use crate::model::{
+    BoardState, GameId, MatchId, MatchPlayer, MatchStatus, MinesweeperGame, Point, UserId,
+    VersusMatch,
+};
+use chrono::{DateTime, Utc};
+use sqlx::types::Json;
+use std::collections::HashSet;
+
+/// A `games` row; cell sets, actions and participants are loaded from their own tables.
+#[derive(sqlx::FromRow)]
+pub(super) struct GameRow {
+    pub(super) id: GameId,
+    board: Json<Vec<Vec<BoardState>>>,
+    mine_points: Json<HashSet<Point>>,
+    created_at: DateTime<Utc>,
+    mines_generated: bool,
+    cols: i32,
+    rows: i32,
+    mine_count_target: i32,
+    seed: Option<i64>,
+    no_guess: bool,
+    hints_used: i32,
+    practice: bool,
+    started_at: Option<DateTime<Utc>>,
+    finished_at: Option<DateTime<Utc>>,
+    match_id: Option<MatchId>,
+    invite_code: Option<String>,
+    private: bool,
+    version: i64,
+    owner: Option<UserId>,
+}
+
+impl GameRow {
+    pub(super) fn into_game(self) -> MinesweeperGame {
+        MinesweeperGame {
+            id: self.id,
+            board: self.board.0,
+            moves: HashSet::new(),
+            mine_points: self.mine_points.0,
+            flag_points: HashSet::new(),
+            created_at: self.created_at,
+            mines_generated: self.mines_generated,
+            cols: self.cols as usize,
+            rows: self.rows as usize,
+            mine_count_target: self.mine_count_target as usize,
+            seed: self.seed.map(|seed| seed as u32),
+            no_guess: self.no_guess,
+            hints_used: self.hints_used as u32,
+            practice: self.practice,
+            started_at: self.started_at,
+            finished_at: self.finished_at,
+            actions: Vec::new(),
+            match_id: self.match_id,
+            owner: self.owner,
+            participants: Vec::new(),
+            invite_code: self.invite_code,
+            private: self.private,
+            version: self.version,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+pub(super) struct MatchRow {
+    id: MatchId,
+    owner: UserId,
+    cols: i32,
+    rows: i32,
+    mines: i32,
+    seed: i64,
+    status: String,
+    players: Json<Vec<MatchPlayer>>,
+    winner: Option<UserId>,
+    created_at: DateTime<Utc>,
+    started_at: Option<DateTime<Utc>>,
+    finished_at: Option<DateTime<Utc>>,
+}
+
+impl MatchRow {
+    pub(super) fn into_match(self) -> AppResult<VersusMatch> {
+        Ok(VersusMatch {
+            id: self.id,
+            owner: self.owner,
+            cols: self.cols as usize,
+            rows: self.rows as usize,
+            mines: self.mines as usize,
+            seed: self.seed as u32,
+            status: serde_json::from_value(serde_json::Value::String(self.status))?,
+            players: self.players.0,
+            winner: self.winner,
+            created_at: self.created_at,
+            started_at: self.started_at,
+            finished_at: self.finished_at,
+        })
+    }
+}
+
+/// Updates can only collide on `invite_code`, the one unique column besides the keys.
+pub(super) fn conflict_on_unique_violation(e: sqlx::Error) -> AppError {
+    match &e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => {
+            AppError::Conflict(db.message().to_string())
+        }
+        _ => e.into(),
+    }
+}
+
+/// Statuses are stored by variant name, matching their serde representation.
+pub(super) fn status_name(status: MatchStatus) -> String {
+    format!("{:?}", status)
+}
+
+/// Cell sets live in their own tables so `add_moves`/`add_flag` can rely on the primary
+/// key for the same set semantics Mongo's `$addToSet` gives.
+#[derive(Clone, Copy)]
+pub(super) enum PointTable {
+    Moves,
+    Flags,
+}
+
+impl PointTable {
+    pub(super) fn name(self) -> &'static str {
+        match self {
+            PointTable::Moves => "game_moves",
+            PointTable::Flags => "game_flags",
+        }
+    }
+}
+
+pub(super) fn point(x: i32, y: i32) -> Point {
+    Point {
+        x: x as usize,
+        y: y as usize,
+    }
+}
@@ -0,0 +1,664 @@
+use super::sql::{conflict_on_unique_violation, point, status_name, GameRow, MatchRow, PointTable};
+use crate::error::{AppError, AppResult};
+use crate::model::{
+    ActionKind, GameAction, GameId, MatchId, MatchPlayer, MatchStatus, MinesweeperGame, Point,
+    UserId, VersusMatch, MAX_MATCH_PLAYERS,
+};
+use crate::repository::{GameRepository, MatchRepository, UserGameRepository};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::sqlite::{
+    SqliteConnectOptions, SqliteConnection, SqliteJournalMode, SqlitePool, SqlitePoolOptions,
+};
+use sqlx::types::Json;
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::instrument;
+
+const MAX_CONNECTIONS: u32 = 5;
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// File-backed repository for single-node deployments. Id lists and cell sets are passed
+/// to SQLite as JSON arrays and expanded with `json_each`.
+///
+/// Single-row reads use `fetch_all` rather than `fetch_optional`: the latter leaves its
+/// statement un-reset, which keeps a WAL read snapshot open on the pooled connection and
+/// makes its next reads stale.
+pub struct SqliteGameRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteGameRepository {
+    pub async fn new(path: &str) -> Result<Self, sqlx::Error> {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .busy_timeout(BUSY_TIMEOUT)
+            .foreign_keys(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(MAX_CONNECTIONS)
+            .connect_with(options)
+            .await?;
+        sqlx::migrate!("./migrations/sqlite").run(&pool).await?;
+        Ok(SqliteGameRepository { pool })
+    }
+
+    async fn load_games(
+        conn: &mut SqliteConnection,
+        ids: &[GameId],
+    ) -> AppResult<Vec<MinesweeperGame>> {
+        let ids_json = Json(ids);
+        let rows: Vec<GameRow> =
+            sqlx::query_as("SELECT * FROM games WHERE id IN (SELECT value FROM json_each(?1))")
+                .bind(ids_json)
+                .fetch_all(&mut *conn)
+                .await?;
+        let mut games: HashMap<GameId, MinesweeperGame> = rows
+            .into_iter()
+            .map(|row| (row.id, row.into_game()))
+            .collect();
+        if games.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let moves: Vec<(GameId, i32, i32)> = sqlx::query_as(
+            "SELECT game_id, x, y FROM game_moves WHERE game_id IN (SELECT value FROM json_each(?1))",
+        )
+        .bind(ids_json)
+        .fetch_all(&mut *conn)
+        .await?;
+        for (id, x, y) in moves {
+            if let Some(game) = games.get_mut(&id) {
+                game.moves.insert(point(x, y));
+            }
+        }
+
+        let flags: Vec<(GameId, i32, i32)> = sqlx::query_as(
+            "SELECT game_id, x, y FROM game_flags WHERE game_id IN (SELECT value FROM json_each(?1))",
+        )
+        .bind(ids_json)
+        .fetch_all(&mut *conn)
+        .await?;
+        for (id, x, y) in flags {
+            if let Some(game) = games.get_mut(&id) {
+                game.flag_points.insert(point(x, y));
+            }
+        }
+
+        let actions: Vec<(GameId, Json<GameAction>)> = sqlx::query_as(
+            "SELECT game_id, action FROM game_actions \
+             WHERE game_id IN (SELECT value FROM json_each(?1)) ORDER BY game_id, seq",
+        )
+        .bind(ids_json)
+        .fetch_all(&mut *conn)
+        .await?;
+        for (id, action) in actions {
+            if let Some(game) = games.get_mut(&id) {
+                game.actions.push(action.0);
+            }
+        }
+
+        let participants: Vec<(GameId, UserId)> = sqlx::query_as(
+            "SELECT game_id, user_id FROM game_participants \
+             WHERE game_id IN (SELECT value FROM json_each(?1)) ORDER BY seq",
+        )
+        .bind(ids_json)
+        .fetch_all(&mut *conn)
+        .await?;
+        for (id, user_id) in participants {
+            if let Some(game) = games.get_mut(&id) {
+                game.participants.push(user_id);
+            }
+        }
+
+        Ok(ids.iter().filter_map(|id| games.remove(id)).collect())
+    }
+
+    async fn load_game(
+        conn: &mut SqliteConnection,
+        id: GameId,
+    ) -> AppResult<Option<MinesweeperGame>> {
+        Ok(Self::load_games(conn, &[id]).await?.pop())
+    }
+
+    /// SQLite has no row locks, so bumping the version takes the database write lock up
+    /// front; a transaction that reads first could fail with `SQLITE_BUSY` when it
+    /// upgrades. Returns the version the write applies on top of, or `None` if the game
+    /// does not exist.
+    async fn lock_game(conn: &mut SqliteConnection, id: GameId) -> AppResult<Option<i64>> {
+        Ok(sqlx::query_scalar(
+            "UPDATE games SET version = version + 1 WHERE id = ?1 RETURNING version - 1",
+        )
+        .bind(id)
+        .fetch_all(&mut *conn)
+        .await?
+        .pop())
+    }
+
+    async fn insert_points(
+        conn: &mut SqliteConnection,
+        table: PointTable,
+        id: GameId,
+        points: &[Point],
+    ) -> AppResult<()> {
+        let sql = format!(
+            "INSERT OR IGNORE INTO {} (game_id, x, y) \
+             SELECT ?1, json_extract(value, '$.x'), json_extract(value, '$.y') FROM json_each(?2)",
+            table.name()
+        );
+        sqlx::query(&sql)
+            .bind(id)
+            .bind(Json(points))
+            .execute(&mut *conn)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_points(
+        conn: &mut SqliteConnection,
+        table: PointTable,
+        id: GameId,
+        points: &[Point],
+    ) -> AppResult<()> {
+        let sql = format!(
+            "DELETE FROM {} WHERE game_id = ?1 AND EXISTS (SELECT 1 FROM json_each(?2) \
+             WHERE json_extract(value, '$.x') = x AND json_extract(value, '$.y') = y)",
+            table.name()
+        );
+        sqlx::query(&sql)
+            .bind(id)
+            .bind(Json(points))
+            .execute(&mut *conn)
+            .await?;
+        Ok(())
+    }
+
+    async fn append_action(
+        conn: &mut SqliteConnection,
+        id: GameId,
+        action: &GameAction,
+    ) -> AppResult<()> {
+        sqlx::query(
+            "INSERT INTO game_actions (game_id, seq, action) \
+             SELECT ?1, COALESCE(MAX(seq), 0) + 1, ?2 FROM game_actions WHERE game_id = ?1",
+        )
+        .bind(id)
+        .bind(Json(action))
+        .execute(&mut *conn)
+        .await?;
+        Ok(())
+    }
+
+    /// Writes the `games` row at the game's version, with `on_conflict` deciding what
+    /// happens to a stored row. Returns whether a row was written.
+    async fn write_game_row(
+        conn: &mut SqliteConnection,
+        game: &MinesweeperGame,
+        on_conflict: &str,
+    ) -> AppResult<bool> {
+        let sql = format!(
+            "INSERT INTO games (id, board, mine_points, created_at, mines_generated, cols, rows, \
+             mine_count_target, seed, no_guess, hints_used, practice, started_at, finished_at, \
+             match_id, invite_code, private, version, owner) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19) {}",
+            on_conflict
+        );
+        let result = sqlx::query(&sql)
+            .bind(game.id)
+            .bind(Json(&game.board))
+            .bind(Json(&game.mine_points))
+            .bind(game.created_at)
+            .bind(game.mines_generated)
+            .bind(game.cols as i32)
+            .bind(game.rows as i32)
+            .bind(game.mine_count_target as i32)
+            .bind(game.seed.map(i64::from))
+            .bind(game.no_guess)
+            .bind(game.hints_used as i32)
+            .bind(game.practice)
+            .bind(game.started_at)
+            .bind(game.finished_at)
+            .bind(game.match_id)
+            .bind(&game.invite_code)
+            .bind(game.private)
+            .bind(game.version)
+            .bind(&game.owner)
+            .execute(&mut *conn)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Writes the cell sets, actions and participants of a game that has none stored.
+    async fn write_game_children(
+        conn: &mut SqliteConnection,
+        game: &MinesweeperGame,
+    ) -> AppResult<()> {
+        let moves: Vec<Point> = game.moves.iter().cloned().collect();
+        Self::insert_points(conn, PointTable::Moves, game.id, &moves).await?;
+        let flags: Vec<Point> = game.flag_points.iter().cloned().collect();
+        Self::insert_points(conn, PointTable::Flags, game.id, &flags).await?;
+        for action in &game.actions {
+            Self::append_action(conn, game.id, action).await?;
+        }
+        for user_id in &game.participants {
+            sqlx::query("INSERT INTO game_participants (game_id, user_id) VALUES (?1, ?2)")
+                .bind(game.id)
+                .bind(user_id)
+                .execute(&mut *conn)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Applies a point-set change and logs its action in one transaction, provided the
+    /// game is still at `version`.
+    async fn record_action(
+        &self,
+        id: GameId,
+        version: i64,
+        table: PointTable,
+        points: &[Point],
+        remove: bool,
+        action: &GameAction,
+    ) -> AppResult<Option<MinesweeperGame>> {
+        let mut tx = self.pool.begin().await?;
+        match Self::lock_game(&mut tx, id).await? {
+            None => return Ok(None),
+            Some(current) if current != version => return Err(AppError::Conflict(id.to_string())),
+            Some(_) => {}
+        }
+
+        if remove {
+            Self::delete_points(&mut tx, table, id, points).await?;
+        } else {
+            Self::insert_points(&mut tx, table, id, points).await?;
+        }
+        Self::append_action(&mut tx, id, action).await?;
+
+        let game = Self::load_game(&mut tx, id).await?;
+        tx.commit().await?;
+        Ok(game)
+    }
+
+    /// Runs a single-row update on `games` and returns the updated game.
+    async fn update_game<'q>(
+        &self,
+        id: GameId,
+        query: sqlx::query::Query<'q, sqlx::Sqlite, sqlx::sqlite::SqliteArguments<'q>>,
+    ) -> AppResult<Option<MinesweeperGame>> {
+        let mut tx = self.pool.begin().await?;
+        let result = query
+            .execute(&mut *tx)
+            .await
+            .map_err(conflict_on_unique_violation)?;
+        if result.rows_affected() == 0 {
+            return Ok(None);
+        }
+        let game = Self::load_game(&mut tx, id).await?;
+        tx.commit().await?;
+        Ok(game)
+    }
+}
+
+#[async_trait]
+impl GameRepository for SqliteGameRepository {
+    async fn ping(&self) -> AppResult<()> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn get_game(&self, id: GameId) -> AppResult<Option<MinesweeperGame>> {
+        let mut conn = self.pool.acquire().await?;
+        Self::load_game(&mut conn, id).await
+    }
+
+    #[instrument(skip(self))]
+    async fn get_games_by_ids(&self, ids: &[GameId]) -> AppResult<Vec<MinesweeperGame>> {
+        let mut conn = self.pool.acquire().await?;
+        Self::load_games(&mut conn, ids).await
+    }
+
+    #[instrument(skip(self, game))]
+    async fn save(&self, mut game: MinesweeperGame) -> AppResult<MinesweeperGame> {
+        let mut tx = self.pool.begin().await?;
+        game.version += 1;
+        // Only a stored game still at the version this one was read at is replaced
+        let replace = "ON CONFLICT (id) DO UPDATE SET board = excluded.board, \
+             mine_points = excluded.mine_points, created_at = excluded.created_at, \
+             mines_generated = excluded.mines_generated, cols = excluded.cols, \
+             rows = excluded.rows, mine_count_target = excluded.mine_count_target, \
+             seed = excluded.seed, no_guess = excluded.no_guess, \
+             hints_used = excluded.hints_used, practice = excluded.practice, \
+             started_at = excluded.started_at, finished_at = excluded.finished_at, \
+             match_id = excluded.match_id, invite_code = excluded.invite_code, \
+             private = excluded.private, version = excluded.version, owner = excluded.owner \
+             WHERE games.version = excluded.version - 1";
+        if !Self::write_game_row(&mut tx, &game, replace).await? {
+            return Err(AppError::Conflict(game.id.to_string()));
+        }
+
+        // A save replaces the whole document, child rows included
+        for table in [
+            "game_moves",
+            "game_flags",
+            "game_actions",
+            "game_participants",
+        ] {
+            sqlx::query(&format!("DELETE FROM {} WHERE game_id = ?1", table))
+                .bind(game.id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        Self::write_game_children(&mut tx, &game).await?;
+
+        tx.commit().await?;
+        Ok(game)
+    }
+
+    #[instrument(skip(self, game))]
+    async fn insert(&self, mut game: MinesweeperGame) -> AppResult<Option<MinesweeperGame>> {
+        let mut tx = self.pool.begin().await?;
+        game.version = 1;
+        if !Self::write_game_row(&mut tx, &game, "ON CONFLICT (id) DO NOTHING").await? {
+            return Ok(None);
+        }
+        Self::write_game_children(&mut tx, &game).await?;
+
+        tx.commit().await?;
+        Ok(Some(game))
+    }
+
+    #[instrument(skip(self, points, action))]
+    async fn add_moves(
+        &self,
+        id: GameId,
+        version: i64,
+        points: &[Point],
+        action: &GameAction,
+    ) -> AppResult<Option<MinesweeperGame>> {
+        self.record_action(id, version, PointTable::Moves, points, false, action)
+            .await
+    }
+
+    #[instrument(skip(self, action))]
+    async fn add_flag(
+        &self,
+        id: GameId,
+        version: i64,
+        point: Point,
+        action: &GameAction,
+    ) -> AppResult<Option<MinesweeperGame>> {
+        self.record_action(id, version, PointTable::Flags, &[point], false, action)
+            .await
+    }
+
+    #[instrument(skip(self, action))]
+    async fn remove_flag(
+        &self,
+        id: GameId,
+        version: i64,
+        point: Point,
+        action: &GameAction,
+    ) -> AppResult<Option<MinesweeperGame>> {
+        self.record_action(id, version, PointTable::Flags, &[point], true, action)
+            .await
+    }
+
+    #[instrument(skip(self))]
+    async fn add_participant(
+        &self,
+        id: GameId,
+        user_id: &UserId,
+    ) -> AppResult<Option<MinesweeperGame>> {
+        let mut tx = self.pool.begin().await?;
+        if Self::lock_game(&mut tx, id).await?.is_none() {
+            return Ok(None);
+        }
+
+        sqlx::query("INSERT OR IGNORE INTO game_participants (game_id, user_id) VALUES (?1, ?2)")
+            .bind(id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let game = Self::load_game(&mut tx, id).await?;
+        tx.commit().await?;
+        Ok(game)
+    }
+
+    #[instrument(skip(self))]
+    async fn set_invite_code(&self, id: GameId, code: &str) -> AppResult<Option<MinesweeperGame>> {
+        let query =
+            sqlx::query("UPDATE games SET invite_code = ?2, version = version + 1 WHERE id = ?1")
+                .bind(id)
+                .bind(code);
+        self.update_game(id, query).await
+    }
+
+    #[instrument(skip(self))]
+    async fn set_private(&self, id: GameId, private: bool) -> AppResult<Option<MinesweeperGame>> {
+        let query =
+            sqlx::query("UPDATE games SET private = ?2, version = version + 1 WHERE id = ?1")
+                .bind(id)
+                .bind(private);
+        self.update_game(id, query).await
+    }
+
+    #[instrument(skip(self))]
+    async fn get_game_by_invite_code(&self, code: &str) -> AppResult<Option<MinesweeperGame>> {
+        let mut conn = self.pool.acquire().await?;
+        let id: Option<GameId> = sqlx::query_scalar("SELECT id FROM games WHERE invite_code = ?1")
+            .bind(code)
+            .fetch_all(&mut *conn)
+            .await?
+            .pop();
+        match id {
+            Some(id) => Self::load_game(&mut conn, id).await,
+            None => Ok(None),
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn remove_last_action(
+        &self,
+        id: GameId,
+    ) -> AppResult<Option<(MinesweeperGame, Option<GameAction>)>> {
+        let mut tx = self.pool.begin().await?;
+        if Self::lock_game(&mut tx, id).await?.is_none() {
+            return Ok(None);
+        }
+
+        let last: Option<(i32, Json<GameAction>)> = sqlx::query_as(
+            "SELECT seq, action FROM game_actions WHERE game_id = ?1 ORDER BY seq DESC LIMIT 1",
+        )
+        .bind(id)
+        .fetch_all(&mut *tx)
+        .await?
+        .pop();
+
+        if let Some((seq, Json(action))) = &last {
+            sqlx::query("DELETE FROM game_actions WHERE game_id = ?1 AND seq = ?2")
+                .bind(id)
+                .bind(*seq)
+                .execute(&mut *tx)
+                .await?;
+            match action.kind {
+                ActionKind::Reveal | ActionKind::Chord => {
+                    Self::delete_points(&mut tx, PointTable::Moves, id, &action.revealed).await?;
+                }
+                ActionKind::Flag => {
+                    Self::delete_points(&mut tx, PointTable::Flags, id, &[action.point]).await?;
+                }
+                ActionKind::Unflag => {
+                    Self::insert_points(&mut tx, PointTable::Flags, id, &[action.point]).await?;
+                }
+            }
+            sqlx::query("UPDATE games SET finished_at = NULL WHERE id = ?1")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        let game = Self::load_game(&mut tx, id).await?;
+        tx.commit().await?;
+        Ok(game.map(|game| (game, last.map(|(_, Json(action))| action))))
+    }
+
+    #[instrument(skip(self))]
+    async fn add_hint(&self, id: GameId) -> AppResult<Option<MinesweeperGame>> {
+        let query = sqlx::query(
+            "UPDATE games SET hints_used = hints_used + 1, version = version + 1 WHERE id = ?1",
+        )
+        .bind(id);
+        self.update_game(id, query).await
+    }
+
+    #[instrument(skip(self))]
+    async fn mark_finished(
+        &self,
+        id: GameId,
+        finished_at: DateTime<Utc>,
+    ) -> AppResult<Option<MinesweeperGame>> {
+        // Only the first transition to game over sets the timestamp
+        sqlx::query("UPDATE games SET finished_at = ?2, version = version + 1 WHERE id = ?1 AND finished_at IS NULL")
+            .bind(id)
+            .bind(finished_at)
+            .execute(&self.pool)
+            .await?;
+        self.get_game(id).await
+    }
+}
+
+#[async_trait]
+impl UserGameRepository for SqliteGameRepository {
+    async fn add_mapping(&self, user_id: &UserId, game_id: GameId) -> AppResult<()> {
+        sqlx::query("INSERT OR IGNORE INTO user_games (user_id, game_id) VALUES (?1, ?2)")
+            .bind(user_id)
+            .bind(game_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_game_ids_by_user_id(&self, user_id: &UserId) -> AppResult<Vec<GameId>> {
+        Ok(
+            sqlx::query_scalar("SELECT game_id FROM user_games WHERE user_id = ?1 ORDER BY seq")
+                .bind(user_id)
+                .fetch_all(&self.pool)
+                .await?,
+        )
+    }
+}
+
+#[async_trait]
+impl MatchRepository for SqliteGameRepository {
+    #[instrument(skip(self))]
+    async fn get_match(&self, id: MatchId) -> AppResult<Option<VersusMatch>> {
+        let row: Option<MatchRow> = sqlx::query_as("SELECT * FROM matches WHERE id = ?1")
+            .bind(id)
+            .fetch_all(&self.pool)
+            .await?
+            .pop();
+        row.map(MatchRow::into_match).transpose()
+    }
+
+    #[instrument(skip(self, versus))]
+    async fn insert_match(&self, versus: VersusMatch) -> AppResult<Option<VersusMatch>> {
+        let result = sqlx::query(
+            "INSERT INTO matches (id, owner, cols, rows, mines, seed, status, players, winner, \
+             created_at, started_at, finished_at) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12) \
+             ON CONFLICT (id) DO NOTHING",
+        )
+        .bind(versus.id)
+        .bind(&versus.owner)
+        .bind(versus.cols as i32)
+        .bind(versus.rows as i32)
+        .bind(versus.mines as i32)
+        .bind(versus.seed as i64)
+        .bind(status_name(versus.status))
+        .bind(Json(&versus.players))
+        .bind(&versus.winner)
+        .bind(versus.created_at)
+        .bind(versus.started_at)
+        .bind(versus.finished_at)
+        .execute(&self.pool)
+        .await?;
+        Ok((result.rows_affected() > 0).then_some(versus))
+    }
+
+    #[instrument(skip(self))]
+    async fn add_match_player(
+        &self,
+        id: MatchId,
+        user_id: &UserId,
+    ) -> AppResult<Option<VersusMatch>> {
+        let player = MatchPlayer {
+            user_id: user_id.clone(),
+            game_id: None,
+        };
+        // Capacity, duplicate and state checks live in the WHERE clause so joins stay atomic
+        let row: Option<MatchRow> = sqlx::query_as(
+            "UPDATE matches SET players = json_insert(players, '$[#]', json(?2)) \
+             WHERE id = ?1 AND status = ?3 AND json_array_length(players) < ?4 \
+             AND NOT EXISTS (SELECT 1 FROM json_each(players) \
+             WHERE json_extract(value, '$.UserId') = ?5) RETURNING *",
+        )
+        .bind(id)
+        .bind(Json(&player))
+        .bind(status_name(MatchStatus::Waiting))
+        .bind(MAX_MATCH_PLAYERS as i32)
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?
+        .pop();
+        row.map(MatchRow::into_match).transpose()
+    }
+
+    #[instrument(skip(self, players))]
+    async fn start_match(
+        &self,
+        id: MatchId,
+        players: &[MatchPlayer],
+        started_at: DateTime<Utc>,
+    ) -> AppResult<Option<VersusMatch>> {
+        let row: Option<MatchRow> = sqlx::query_as(
+            "UPDATE matches SET players = ?2, status = ?3, started_at = ?4 \
+             WHERE id = ?1 AND status = ?5 RETURNING *",
+        )
+        .bind(id)
+        .bind(Json(players))
+        .bind(status_name(MatchStatus::InProgress))
+        .bind(started_at)
+        .bind(status_name(MatchStatus::Waiting))
+        .fetch_all(&self.pool)
+        .await?
+        .pop();
+        row.map(MatchRow::into_match).transpose()
+    }
+
+    #[instrument(skip(self))]
+    async fn finish_match(
+        &self,
+        id: MatchId,
+        winner: Option<UserId>,
+        finished_at: DateTime<Utc>,
+    ) -> AppResult<Option<VersusMatch>> {
+        let row: Option<MatchRow> = sqlx::query_as(
+            "UPDATE matches SET status = ?2, winner = ?3, finished_at = ?4 \
+             WHERE id = ?1 AND status = ?5 RETURNING *",
+        )
+        .bind(id)
+        .bind(status_name(MatchStatus::Finished))
+        .bind(winner)
+        .bind(finished_at)
+        .bind(status_name(MatchStatus::InProgress))
+        .fetch_all(&self.pool)
+        .await?
+        .pop();
+        row.map(MatchRow::into_match).transpose()
+    }
+}
@@ -1,44 +1,166 @@
-use super::GameService;
-use crate::engine::BoardEngine;
+use super::{GameIdGenerator, GameService, RandomGameIds};
+use crate::engine::{probability, replay, solver, BoardEngine, NO_GUESS_MAX_CELLS};
 use crate::error::{AppError, AppResult};
-use crate::model::{MinesweeperGame, Point, UserInfo, UserStatsDto};
+use crate::events::Hub;
+use crate::model::{
+    ActionKind, BestTimeDto, GameAction, GameId, GameOptions, GameUpdateDto, HintDto,
+    InviteCodeDto, MatchDto, MatchId, MinesweeperGame, Point, ProbabilitiesDto, ReplayDto, UserId,
+    UserInfo, UserStatsDto,
+};
 use crate::repository::MinesweeperRepository;
 use crate::telemetry::metrics::MinesweeperMetrics;
 use async_trait::async_trait;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::future::Future;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+pub const MAX_SPECTATORS_PER_GAME: usize = 50;
+const CONFLICT_ATTEMPTS: usize = 3;
+pub(super) const ID_ATTEMPTS: usize = 5;
 
 pub struct MinesweeperService {
-    repo: Arc<dyn MinesweeperRepository>,
-    engine: Arc<dyn BoardEngine>,
+    pub(super) repo: Arc<dyn MinesweeperRepository>,
+    pub(super) engine: Arc<dyn BoardEngine>,
+    game_ids: Arc<dyn GameIdGenerator>,
+    pub(super) match_events: Hub<MatchId, MatchDto>,
+    game_events: Hub<GameId, GameUpdateDto>,
+    spectator_events: Hub<GameId, GameUpdateDto>,
 }
 
 impl MinesweeperService {
     pub fn new(repo: Arc<dyn MinesweeperRepository>, engine: Arc<dyn BoardEngine>) -> Self {
-        Self { repo, engine }
+        Self {
+            repo,
+            engine,
+            game_ids: Arc::new(RandomGameIds),
+            match_events: Hub::new(),
+            game_events: Hub::new(),
+            spectator_events: Hub::new(),
+        }
+    }
+
+    pub fn with_game_ids(mut self, game_ids: Arc<dyn GameIdGenerator>) -> Self {
+        self.game_ids = game_ids;
+        self
     }
 
-    async fn fetch_game(&self, id: i32) -> AppResult<MinesweeperGame> {
-        if id == 0 {
-            return Err(AppError::BadRequest("Game ID is required".to_string()));
+    /// Stores a new game under the first free id the generator comes up with.
+    pub(super) async fn insert_game(
+        &self,
+        mut game: MinesweeperGame,
+    ) -> AppResult<MinesweeperGame> {
+        for _ in 0..ID_ATTEMPTS {
+            game.id = self.game_ids.next_id();
+            if let Some(game) = self.repo.insert(game.clone()).await? {
+                return Ok(game);
+            }
+            tracing::warn!("Game id {} is already taken, picking another", game.id);
         }
+
+        Err(AppError::Internal(
+            "Could not allocate a free game id".to_string(),
+        ))
+    }
+
+    /// Gives the game a fresh invite code, drawing another if one is already in use.
+    async fn assign_invite_code(&self, id: GameId) -> AppResult<String> {
+        for _ in 0..ID_ATTEMPTS {
+            let code = uuid::Uuid::new_v4().simple().to_string();
+            match self.repo.set_invite_code(id, &code).await {
+                Ok(game) => {
+                    return game
+                        .and_then(|g| g.invite_code)
+                        .ok_or_else(|| AppError::NotFound(id.to_string()))
+                }
+                Err(AppError::Conflict(_)) => {
+                    tracing::warn!(
+                        "Invite code for game {} is already taken, picking another",
+                        id
+                    );
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(AppError::Internal(
+            "Could not allocate a free invite code".to_string(),
+        ))
+    }
+
+    /// Lays out the mines around the first click on the blocking pool, since guess-free
+    /// layouts can take several solver passes. Also returns how long that took, for the
+    /// caller to record once the layout has been stored.
+    pub(super) async fn generate_mines(
+        &self,
+        mut game: MinesweeperGame,
+        first_click: Point,
+    ) -> AppResult<(MinesweeperGame, Duration)> {
+        let engine = self.engine.clone();
+        let started = Instant::now();
+        let game = tokio::task::spawn_blocking(move || {
+            engine.generate_mines(&mut game, first_click);
+            game
+        })
+        .await
+        .map_err(|e| AppError::Internal(format!("Mine generation failed: {}", e)))?;
+        Ok((game, started.elapsed()))
+    }
+
+    pub(super) async fn fetch_game(&self, id: GameId) -> AppResult<MinesweeperGame> {
         self.repo
             .get_game(id)
             .await?
             .ok_or_else(|| AppError::NotFound(id.to_string()))
     }
 
-    async fn check_ownership(&self, game_id: i32, user: Option<UserInfo>) -> AppResult<()> {
-        let owner_id = self.repo.get_game_owner(game_id).await?;
+    /// Marks a game that has just been won or lost as finished, emitting its outcome the
+    /// one time it transitions.
+    async fn finish_if_over(
+        &self,
+        game: MinesweeperGame,
+        user: Option<&UserInfo>,
+    ) -> AppResult<MinesweeperGame> {
+        if !game.is_game_over() || game.finished_at.is_some() {
+            return Ok(game);
+        }
+
+        let game = self
+            .repo
+            .mark_finished(game.id, Utc::now())
+            .await?
+            .ok_or_else(|| AppError::NotFound(game.id.to_string()))?;
+
+        tracing::info!(
+            game_id = %game.id,
+            outcome = ?game.status(),
+            cols = game.cols,
+            rows = game.rows,
+            mines = game.mine_count_target,
+            duration_ms = game.elapsed_ms(),
+            cells_revealed = game.moves.len(),
+            authenticated = user.is_some(),
+            "Game finished"
+        );
+        MinesweeperMetrics::record_game_finished(&game, user.is_some());
+
+        Ok(game)
+    }
+
+    fn check_ownership(&self, game: &MinesweeperGame, user: Option<&UserInfo>) -> AppResult<()> {
+        let owner_id = game.owner.as_ref();
         
         tracing::debug!(
             "Checking ownership: game_id={}, owner_id={:?}, user={:?}",
-            game_id,
+            game.id,
             owner_id,
             user
         );
 
         match (owner_id, user) {
-            (Some(owner), Some(u)) if owner != u.sub => {
+            (Some(owner), Some(u)) if *owner != u.sub && !game.is_participant(&u.sub) => {
                 tracing::warn!("Unauthorized: owner={}, user={}", owner, u.sub);
                 Err(AppError::Unauthorized)
             }
@@ -49,41 +171,68 @@ impl MinesweeperService {
             _ => Ok(()),
         }
     }
-}
 
-#[async_trait]
-impl GameService for MinesweeperService {
-    async fn get_game(&self, id: i32) -> AppResult<MinesweeperGame> {
-        self.fetch_game(id).await
+    /// Private games can only be seen by their players; signed-in strangers are told
+    /// they may not rather than asked to sign in.
+    fn check_can_view(&self, game: &MinesweeperGame, user: Option<&UserInfo>) -> AppResult<()> {
+        if !game.private {
+            return Ok(());
+        }
+
+        match user {
+            Some(u) if game.owner.as_ref() != Some(&u.sub) && !game.is_participant(&u.sub) => {
+                Err(AppError::Forbidden("This game is private".to_string()))
+            }
+            Some(_) => Ok(()),
+            None => Err(AppError::Unauthorized),
+        }
     }
 
-    async fn create_game(
-        &self,
-        cols: usize,
-        rows: usize,
-        mines: usize,
-        user: Option<UserInfo>,
-    ) -> AppResult<MinesweeperGame> {
-        let game = MinesweeperGame::new(cols, rows, mines);
-        self.repo.save(game.clone()).await?;
+    fn publish_update(&self, game: &MinesweeperGame, action: &GameAction) -> GameUpdateDto {
+        let update = GameUpdateDto::new(game, &action.changed_points(), action.user_id.clone());
+        self.publish(update.clone());
+        update
+    }
 
-        if let Some(user_info) = user {
-            self.repo.add_mapping(&user_info.sub, game.id).await?;
+    /// Spectators may be strangers, so only game event subscribers learn who moved.
+    fn publish(&self, update: GameUpdateDto) {
+        let anonymous = GameUpdateDto {
+            user_id: None,
+            ..update.clone()
+        };
+        self.spectator_events.publish(update.id, anonymous);
+        self.game_events.publish(update.id, update);
+    }
+
+    fn check_owner(&self, game: &MinesweeperGame, user: &UserInfo) -> AppResult<()> {
+        match &game.owner {
+            Some(owner) if *owner == user.sub => Ok(()),
+            Some(_) => Err(AppError::Forbidden(
+                "Only the game owner can change this".to_string(),
+            )),
+            None => Err(AppError::BadRequest("Game has no owner".to_string())),
         }
+    }
 
-        MinesweeperMetrics::record_game_started();
+    /// Only the owner of a solo game can share it; match boards stay single-player.
+    fn check_can_share(&self, game: &MinesweeperGame, user: &UserInfo) -> AppResult<()> {
+        if game.match_id.is_some() {
+            return Err(AppError::BadRequest(
+                "Match games cannot be shared".to_string(),
+            ));
+        }
 
-        Ok(game)
+        self.check_owner(game, user)
     }
 
-    async fn make_move(
+    async fn try_make_move(
         &self,
-        id: i32,
+        id: GameId,
         point: Point,
         user: Option<UserInfo>,
-    ) -> AppResult<MinesweeperGame> {
-        self.check_ownership(id, user).await?;
+    ) -> AppResult<(MinesweeperGame, GameUpdateDto)> {
         let mut game = self.fetch_game(id).await?;
+        self.check_ownership(&game, user.as_ref())?;
 
         if !game.is_valid_point(&point) {
             return Err(AppError::BadRequest(
@@ -92,34 +241,91 @@ impl GameService for MinesweeperService {
         }
 
         if game.is_game_over() || game.is_point_revealed(&point) || game.is_point_flagged(&point) {
-            return Ok(game);
+            return Ok(unchanged(game));
         }
 
         if !game.mines_generated {
-            self.engine.generate_mines(&mut game, point);
-            self.repo.save(game.clone()).await?;
+            let (generated, elapsed) = self.generate_mines(game, point).await?;
+            game = generated;
+            game.started_at = Some(Utc::now());
+            // A concurrent first click that saved its own layout first makes this conflict
+            game = self.repo.save(game).await?;
+            MinesweeperMetrics::record_mine_generation(&game, elapsed);
         }
 
         let reveal_points = self.engine.get_reveal_points(&game, point);
-        let updated_game = self.repo.add_moves(id, &reveal_points).await?;
+        let action = GameAction::new(ActionKind::Reveal, point, game.unrevealed(&reveal_points))
+            .with_user(user.as_ref());
+        let updated_game = self
+            .repo
+            .add_moves(id, game.version, &reveal_points, &action)
+            .await?;
 
-        match updated_game {
+        let game = match updated_game {
             Some(g) => {
-                MinesweeperMetrics::record_move(&g);
-                Ok(g)
+                MinesweeperMetrics::record_move(&g, &action, user.is_some());
+                self.finish_if_over(g, user.as_ref()).await?
             }
-            None => Err(AppError::NotFound(id.to_string())),
+            None => return Err(AppError::NotFound(id.to_string())),
+        };
+
+        let update = self.publish_update(&game, &action);
+        self.update_match_progress(&game).await?;
+        Ok((game, update))
+    }
+
+    async fn try_chord(
+        &self,
+        id: GameId,
+        point: Point,
+        user: Option<UserInfo>,
+    ) -> AppResult<(MinesweeperGame, GameUpdateDto)> {
+        let game = self.fetch_game(id).await?;
+        self.check_ownership(&game, user.as_ref())?;
+
+        if !game.is_valid_point(&point) {
+            return Err(AppError::BadRequest(
+                "Coordinates out of bounds".to_string(),
+            ));
         }
+
+        if game.is_game_over() {
+            return Ok(unchanged(game));
+        }
+
+        let reveal_points = self.engine.get_chord_points(&game, point);
+        if reveal_points.is_empty() {
+            return Ok(unchanged(game));
+        }
+
+        let action = GameAction::new(ActionKind::Chord, point, game.unrevealed(&reveal_points))
+            .with_user(user.as_ref());
+        let updated_game = self
+            .repo
+            .add_moves(id, game.version, &reveal_points, &action)
+            .await?;
+
+        let game = match updated_game {
+            Some(g) => {
+                MinesweeperMetrics::record_move(&g, &action, user.is_some());
+                self.finish_if_over(g, user.as_ref()).await?
+            }
+            None => return Err(AppError::NotFound(id.to_string())),
+        };
+
+        let update = self.publish_update(&game, &action);
+        self.update_match_progress(&game).await?;
+        Ok((game, update))
     }
 
-    async fn toggle_flag(
+    async fn try_toggle_flag(
         &self,
-        id: i32,
+        id: GameId,
         point: Point,
         user: Option<UserInfo>,
-    ) -> AppResult<MinesweeperGame> {
-        self.check_ownership(id, user).await?;
+    ) -> AppResult<(MinesweeperGame, GameUpdateDto)> {
         let game = self.fetch_game(id).await?;
+        self.check_ownership(&game, user.as_ref())?;
 
         if !game.is_valid_point(&point) {
             return Err(AppError::BadRequest(
@@ -128,16 +334,324 @@ impl GameService for MinesweeperService {
         }
 
         if game.is_game_over() || game.is_point_revealed(&point) {
-            return Ok(game);
+            return Ok(unchanged(game));
         }
 
         let updated_game = if game.is_point_flagged(&point) {
-            self.repo.remove_flag(id, point).await?
+            let action =
+                GameAction::new(ActionKind::Unflag, point, Vec::new()).with_user(user.as_ref());
+            self.repo
+                .remove_flag(id, game.version, point, &action)
+                .await?
+                .map(|g| (g, action))
         } else {
-            self.repo.add_flag(id, point).await?
+            let action =
+                GameAction::new(ActionKind::Flag, point, Vec::new()).with_user(user.as_ref());
+            self.repo
+                .add_flag(id, game.version, point, &action)
+                .await?
+                .map(|g| (g, action))
         };
 
-        updated_game.ok_or_else(|| AppError::NotFound(id.to_string()))
+        let (game, action) = updated_game.ok_or_else(|| AppError::NotFound(id.to_string()))?;
+        MinesweeperMetrics::record_move(&game, &action, user.is_some());
+        let update = self.publish_update(&game, &action);
+        Ok((game, update))
+    }
+}
+
+#[async_trait]
+impl GameService for MinesweeperService {
+    async fn get_game(&self, id: GameId, user: Option<UserInfo>) -> AppResult<MinesweeperGame> {
+        let game = self.fetch_game(id).await?;
+        self.check_can_view(&game, user.as_ref())?;
+        Ok(game)
+    }
+
+    async fn create_game(
+        &self,
+        cols: usize,
+        rows: usize,
+        mines: usize,
+        options: GameOptions,
+        user: Option<UserInfo>,
+    ) -> AppResult<MinesweeperGame> {
+        if options.no_guess && cols * rows > NO_GUESS_MAX_CELLS {
+            return Err(AppError::BadRequest(format!(
+                "No-guess games are limited to {} cells",
+                NO_GUESS_MAX_CELLS
+            )));
+        }
+
+        let mut game = MinesweeperGame::new(cols, rows, mines, &options);
+        game.owner = user.as_ref().map(|u| u.sub.clone());
+        let game = self.insert_game(game).await?;
+
+        if let Some(user_info) = &user {
+            self.repo.add_mapping(&user_info.sub, game.id).await?;
+        }
+
+        MinesweeperMetrics::record_game_started(&game, user.is_some());
+
+        Ok(game)
+    }
+
+    async fn make_move(
+        &self,
+        id: GameId,
+        point: Point,
+        user: Option<UserInfo>,
+    ) -> AppResult<(MinesweeperGame, GameUpdateDto)> {
+        timed_move(
+            "reveal",
+            retry_on_conflict(|| self.try_make_move(id, point, user.clone())),
+        )
+        .await
+    }
+
+    async fn chord(
+        &self,
+        id: GameId,
+        point: Point,
+        user: Option<UserInfo>,
+    ) -> AppResult<(MinesweeperGame, GameUpdateDto)> {
+        timed_move(
+            "chord",
+            retry_on_conflict(|| self.try_chord(id, point, user.clone())),
+        )
+        .await
+    }
+
+    async fn toggle_flag(
+        &self,
+        id: GameId,
+        point: Point,
+        user: Option<UserInfo>,
+    ) -> AppResult<(MinesweeperGame, GameUpdateDto)> {
+        timed_move(
+            "flag",
+            retry_on_conflict(|| self.try_toggle_flag(id, point, user.clone())),
+        )
+        .await
+    }
+
+    async fn invite_player(
+        &self,
+        id: GameId,
+        invitee: &UserId,
+        user: UserInfo,
+    ) -> AppResult<MinesweeperGame> {
+        let game = self.fetch_game(id).await?;
+        self.check_can_share(&game, &user)?;
+
+        if invitee.is_empty() || *invitee == user.sub {
+            return Err(AppError::BadRequest("Invalid invitee".to_string()));
+        }
+
+        self.repo
+            .add_participant(id, invitee)
+            .await?
+            .ok_or_else(|| AppError::NotFound(id.to_string()))
+    }
+
+    async fn create_invite_code(&self, id: GameId, user: UserInfo) -> AppResult<InviteCodeDto> {
+        let game = self.fetch_game(id).await?;
+        self.check_can_share(&game, &user)?;
+
+        let invite_code = match game.invite_code {
+            Some(code) => code,
+            None => self.assign_invite_code(id).await?,
+        };
+
+        Ok(InviteCodeDto { id, invite_code })
+    }
+
+    async fn join_game(&self, code: &str, user: UserInfo) -> AppResult<MinesweeperGame> {
+        let game = self
+            .repo
+            .get_game_by_invite_code(code)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Invite code".to_string()))?;
+
+        if game.is_participant(&user.sub) || game.owner.as_ref() == Some(&user.sub) {
+            return Ok(game);
+        }
+
+        self.repo
+            .add_participant(game.id, &user.sub)
+            .await?
+            .ok_or_else(|| AppError::NotFound(game.id.to_string()))
+    }
+
+    async fn undo(&self, id: GameId, user: Option<UserInfo>) -> AppResult<MinesweeperGame> {
+        let game = self.fetch_game(id).await?;
+        self.check_ownership(&game, user.as_ref())?;
+
+        if !game.practice {
+            return Err(AppError::Forbidden(
+                "Undo is only available for practice games".to_string(),
+            ));
+        }
+
+        let (undone, removed) = self
+            .repo
+            .remove_last_action(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(id.to_string()))?;
+
+        if let Some(action) = removed {
+            let update = GameUpdateDto::new(&undone, &action.changed_points(), user.map(|u| u.sub));
+            self.publish(update);
+        }
+
+        Ok(undone)
+    }
+
+    async fn subscribe_game(
+        &self,
+        id: GameId,
+        user: Option<UserInfo>,
+    ) -> AppResult<(MinesweeperGame, broadcast::Receiver<GameUpdateDto>)> {
+        let game = self.fetch_game(id).await?;
+        self.check_ownership(&game, user.as_ref())?;
+
+        // Subscribe before re-reading so no update can slip in between
+        let updates = self.game_events.subscribe(id);
+        let game = self.fetch_game(id).await?;
+        Ok((game, updates))
+    }
+
+    async fn spectate_game(
+        &self,
+        id: GameId,
+        user: Option<UserInfo>,
+    ) -> AppResult<(GameUpdateDto, broadcast::Receiver<GameUpdateDto>)> {
+        let game = self.fetch_game(id).await?;
+        self.check_can_view(&game, user.as_ref())?;
+
+        let updates = self
+            .spectator_events
+            .subscribe_limited(id, MAX_SPECTATORS_PER_GAME)
+            .ok_or_else(|| {
+                AppError::TooManyRequests("Too many spectators for this game".to_string())
+            })?;
+        let game = self.fetch_game(id).await?;
+        Ok((GameUpdateDto::snapshot(&game), updates))
+    }
+
+    async fn set_private(
+        &self,
+        id: GameId,
+        private: bool,
+        user: UserInfo,
+    ) -> AppResult<MinesweeperGame> {
+        let game = self.fetch_game(id).await?;
+        self.check_owner(&game, &user)?;
+
+        self.repo
+            .set_private(id, private)
+            .await?
+            .ok_or_else(|| AppError::NotFound(id.to_string()))
+    }
+
+    async fn get_hint(&self, id: GameId, user: Option<UserInfo>) -> AppResult<HintDto> {
+        let game = self.fetch_game(id).await?;
+        self.check_ownership(&game, user.as_ref())?;
+
+        if game.match_id.is_some() {
+            return Err(AppError::Forbidden(
+                "Hints are not available in matches".to_string(),
+            ));
+        }
+
+        let point = if game.is_game_over() {
+            None
+        } else if !game.mines_generated {
+            // The first click is always surrounded by a safe zone
+            Some(Point {
+                x: game.cols / 2,
+                y: game.rows / 2,
+            })
+        } else {
+            solver::find_safe_cell(&game.visible_board(), game.mine_count())
+        };
+
+        let hints_used = match point {
+            Some(_) => {
+                self.repo
+                    .add_hint(id)
+                    .await?
+                    .ok_or_else(|| AppError::NotFound(id.to_string()))?
+                    .hints_used
+            }
+            None => game.hints_used,
+        };
+
+        Ok(HintDto { point, hints_used })
+    }
+
+    async fn get_probabilities(
+        &self,
+        id: GameId,
+        user: Option<UserInfo>,
+    ) -> AppResult<ProbabilitiesDto> {
+        let game = self.fetch_game(id).await?;
+        self.check_ownership(&game, user.as_ref())?;
+
+        if !game.allows_analysis() {
+            return Err(AppError::Forbidden(
+                "Probabilities are only available for practice or finished games".to_string(),
+            ));
+        }
+
+        let probabilities =
+            probability::mine_probabilities(&game.visible_board(), game.mine_count()).ok_or_else(
+                || AppError::BadRequest("Board is too complex to analyse".to_string()),
+            )?;
+
+        Ok(ProbabilitiesDto {
+            id: game.id,
+            probabilities,
+        })
+    }
+
+    async fn get_replay(
+        &self,
+        id: GameId,
+        step: Option<usize>,
+        user: Option<UserInfo>,
+    ) -> AppResult<ReplayDto> {
+        let game = self.fetch_game(id).await?;
+        self.check_ownership(&game, user.as_ref())?;
+
+        if !game.allows_analysis() {
+            return Err(AppError::Forbidden(
+                "Replays are only available for practice or finished games".to_string(),
+            ));
+        }
+
+        if step.is_some_and(|s| s > game.actions.len()) {
+            return Err(AppError::BadRequest(format!(
+                "Step must be between 0 and {}",
+                game.actions.len()
+            )));
+        }
+
+        let visible_board =
+            step.map(|s| replay::replay(self.engine.as_ref(), &game, s).visible_board());
+
+        Ok(ReplayDto {
+            id: game.id,
+            seed: game.seed,
+            cols: game.cols,
+            rows: game.rows,
+            mine_count: game.mine_count(),
+            no_guess: game.no_guess,
+            board: game.board.clone(),
+            actions: game.actions,
+            step,
+            visible_board,
+        })
     }
 
     async fn get_user_games(&self, user: UserInfo) -> AppResult<Vec<MinesweeperGame>> {
@@ -152,10 +666,23 @@ impl GameService for MinesweeperService {
         let mut won = 0;
         let mut lost = 0;
         let mut in_progress = 0;
+        let mut assisted_won = 0;
+        let mut best_times: HashMap<(usize, usize, usize), i64> = HashMap::new();
 
-        for game in games {
+        // Practice games can be undone, so they never count towards ranked statistics
+        for game in games.into_iter().filter(|g| !g.practice) {
             if game.is_game_won() {
                 won += 1;
+                if game.hints_used > 0 {
+                    assisted_won += 1;
+                }
+                // Hinted wins count as assisted, never towards the best times
+                if game.finished_at.is_some() && game.hints_used == 0 {
+                    let best = best_times
+                        .entry((game.cols, game.rows, game.mine_count_target))
+                        .or_insert(i64::MAX);
+                    *best = (*best).min(game.elapsed_ms());
+                }
             } else if game.is_game_lost() {
                 lost += 1;
             } else {
@@ -163,10 +690,64 @@ impl GameService for MinesweeperService {
             }
         }
 
+        let mut best_times: Vec<BestTimeDto> = best_times
+            .into_iter()
+            .map(|((cols, rows, mines), elapsed_ms)| BestTimeDto {
+                cols,
+                rows,
+                mines,
+                elapsed_ms,
+            })
+            .collect();
+        best_times.sort_by_key(|t| (t.cols * t.rows, t.mines, t.cols));
+
         Ok(UserStatsDto {
             won,
             lost,
             in_progress,
+            assisted_won,
+            best_times,
         })
     }
 }
+
+/// Result of an action that changed nothing, e.g. clicking an already revealed cell.
+fn unchanged(game: MinesweeperGame) -> (MinesweeperGame, GameUpdateDto) {
+    let update = GameUpdateDto::new(&game, &[], None);
+    (game, update)
+}
+
+/// Records how long a move took once it succeeds, conflict retries included.
+async fn timed_move<Fut>(
+    action: &'static str,
+    op: Fut,
+) -> AppResult<(MinesweeperGame, GameUpdateDto)>
+where
+    Fut: Future<Output = AppResult<(MinesweeperGame, GameUpdateDto)>>,
+{
+    let started = Instant::now();
+    let result = op.await;
+    if let Ok((game, _)) = &result {
+        MinesweeperMetrics::record_move_duration(action, game, started.elapsed());
+    }
+    result
+}
+
+/// Re-runs an operation from a fresh read of the game when a concurrent write got there
+/// first, giving up with the conflict after a few attempts.
+async fn retry_on_conflict<T, F, Fut>(mut op: F) -> AppResult<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = AppResult<T>>,
+{
+    let mut attempt = 1;
+    loop {
+        match op().await {
+            Err(AppError::Conflict(id)) if attempt < CONFLICT_ATTEMPTS => {
+                tracing::debug!("Game {} changed concurrently, retrying", id);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
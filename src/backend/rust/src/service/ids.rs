@@ -0,0 +1,15 @@
+use crate::model::GameId;
+
+/// Source of candidate ids for new games. The repository rejects ids that are already
+/// taken and the service asks for another, so generators only need collisions to be rare.
+pub trait GameIdGenerator: Send + Sync {
+    fn next_id(&self) -> GameId;
+}
+
+pub struct RandomGameIds;
+
+impl GameIdGenerator for RandomGameIds {
+    fn next_id(&self) -> GameId {
+        GameId::random()
+    }
+}
@@ -1,33 +1,108 @@
 pub mod game;
+pub mod ids;
+pub mod versus;
 
-pub use game::MinesweeperService;
+pub use game::{MinesweeperService, MAX_SPECTATORS_PER_GAME};
+pub use ids::{GameIdGenerator, RandomGameIds};
 
 use crate::error::AppResult;
-use crate::model::{MinesweeperGame, Point, UserInfo, UserStatsDto};
+use crate::model::{
+    GameId, GameOptions, GameUpdateDto, HintDto, InviteCodeDto, MatchDto, MatchId, MinesweeperGame,
+    Point, ProbabilitiesDto, ReplayDto, UserId, UserInfo, UserStatsDto,
+};
 use async_trait::async_trait;
+use tokio::sync::broadcast;
 
 #[async_trait]
 pub trait GameService: Send + Sync {
-    async fn get_game(&self, id: i32) -> AppResult<MinesweeperGame>;
+    async fn get_game(&self, id: GameId, user: Option<UserInfo>) -> AppResult<MinesweeperGame>;
     async fn create_game(
         &self,
         cols: usize,
         rows: usize,
         mines: usize,
+        options: GameOptions,
         user: Option<UserInfo>,
     ) -> AppResult<MinesweeperGame>;
+    /// Moves return the updated game alongside just the cells the action changed, so
+    /// callers can choose between the full board and an incremental response.
     async fn make_move(
         &self,
-        id: i32,
+        id: GameId,
         point: Point,
         user: Option<UserInfo>,
-    ) -> AppResult<MinesweeperGame>;
+    ) -> AppResult<(MinesweeperGame, GameUpdateDto)>;
+    async fn chord(
+        &self,
+        id: GameId,
+        point: Point,
+        user: Option<UserInfo>,
+    ) -> AppResult<(MinesweeperGame, GameUpdateDto)>;
     async fn toggle_flag(
         &self,
-        id: i32,
+        id: GameId,
         point: Point,
         user: Option<UserInfo>,
+    ) -> AppResult<(MinesweeperGame, GameUpdateDto)>;
+    async fn invite_player(
+        &self,
+        id: GameId,
+        invitee: &UserId,
+        user: UserInfo,
+    ) -> AppResult<MinesweeperGame>;
+    async fn create_invite_code(&self, id: GameId, user: UserInfo) -> AppResult<InviteCodeDto>;
+    async fn join_game(&self, code: &str, user: UserInfo) -> AppResult<MinesweeperGame>;
+    async fn undo(&self, id: GameId, user: Option<UserInfo>) -> AppResult<MinesweeperGame>;
+    /// Subscribes a player to live updates of a game they have write access to.
+    async fn subscribe_game(
+        &self,
+        id: GameId,
+        user: Option<UserInfo>,
+    ) -> AppResult<(MinesweeperGame, broadcast::Receiver<GameUpdateDto>)>;
+    /// Subscribes a read-only spectator, starting from the currently visible cells.
+    async fn spectate_game(
+        &self,
+        id: GameId,
+        user: Option<UserInfo>,
+    ) -> AppResult<(GameUpdateDto, broadcast::Receiver<GameUpdateDto>)>;
+    async fn set_private(
+        &self,
+        id: GameId,
+        private: bool,
+        user: UserInfo,
     ) -> AppResult<MinesweeperGame>;
+    async fn get_hint(&self, id: GameId, user: Option<UserInfo>) -> AppResult<HintDto>;
+    async fn get_probabilities(
+        &self,
+        id: GameId,
+        user: Option<UserInfo>,
+    ) -> AppResult<ProbabilitiesDto>;
+    async fn get_replay(
+        &self,
+        id: GameId,
+        step: Option<usize>,
+        user: Option<UserInfo>,
+    ) -> AppResult<ReplayDto>;
     async fn get_user_games(&self, user: UserInfo) -> AppResult<Vec<MinesweeperGame>>;
     async fn get_user_stats(&self, user: UserInfo) -> AppResult<UserStatsDto>;
 }
+
+#[async_trait]
+pub trait MatchService: Send + Sync {
+    async fn create_match(
+        &self,
+        cols: usize,
+        rows: usize,
+        mines: usize,
+        user: UserInfo,
+    ) -> AppResult<MatchDto>;
+    async fn join_match(&self, id: MatchId, user: UserInfo) -> AppResult<MatchDto>;
+    async fn start_match(&self, id: MatchId, user: UserInfo) -> AppResult<MatchDto>;
+    /// Matches list their players' account ids, so only players may read them.
+    async fn get_match(&self, id: MatchId, user: UserInfo) -> AppResult<MatchDto>;
+    async fn subscribe_match(
+        &self,
+        id: MatchId,
+        user: UserInfo,
+    ) -> AppResult<(MatchDto, broadcast::Receiver<MatchDto>)>;
+}
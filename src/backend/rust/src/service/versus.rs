@@ -0,0 +1,218 @@
+use super::game::ID_ATTEMPTS;
+use super::{MatchService, MinesweeperService};
+use crate::error::{AppError, AppResult};
+use crate::model::versus::{decide_outcome, MatchOutcome};
+use crate::model::{
+    ActionKind, GameAction, GameId, GameOptions, MatchDto, MatchId, MatchPlayer, MatchStatus,
+    MinesweeperGame, Point, UserInfo, VersusMatch,
+};
+use crate::telemetry::metrics::MinesweeperMetrics;
+use async_trait::async_trait;
+use chrono::Utc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+const MIN_MATCH_PLAYERS: usize = 2;
+
+impl MinesweeperService {
+    async fn fetch_match(&self, id: MatchId) -> AppResult<VersusMatch> {
+        self.repo
+            .get_match(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(id.to_string()))
+    }
+
+    fn check_match_player(&self, versus: &VersusMatch, user: &UserInfo) -> AppResult<()> {
+        if versus.has_player(&user.sub) {
+            Ok(())
+        } else {
+            Err(AppError::Forbidden(
+                "Only players can follow this match".to_string(),
+            ))
+        }
+    }
+
+    async fn match_games(&self, versus: &VersusMatch) -> AppResult<Vec<MinesweeperGame>> {
+        let game_ids: Vec<GameId> = versus.players.iter().filter_map(|p| p.game_id).collect();
+        if game_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.repo.get_games_by_ids(&game_ids).await
+    }
+
+    async fn publish_match(&self, versus: &VersusMatch) -> AppResult<MatchDto> {
+        let games = self.match_games(versus).await?;
+        let dto = MatchDto::new(versus, &games);
+        self.match_events.publish(versus.id, dto.clone());
+        Ok(dto)
+    }
+
+    /// Every player gets the same seed with mines generated around the centre cell,
+    /// which is revealed up front so nobody's first click changes their layout. The
+    /// boards are identical, so each copy is private to keep opponents from reading
+    /// safe cells off one another's.
+    async fn create_match_game(
+        &self,
+        versus: &VersusMatch,
+    ) -> AppResult<(MinesweeperGame, Duration)> {
+        let options = GameOptions {
+            seed: Some(versus.seed),
+            private: true,
+            ..Default::default()
+        };
+        let game = MinesweeperGame::new(versus.cols, versus.rows, versus.mines, &options);
+        let opening = Point {
+            x: versus.cols / 2,
+            y: versus.rows / 2,
+        };
+
+        let (mut game, elapsed) = self.generate_mines(game, opening).await?;
+        let revealed = self.engine.get_reveal_points(&game, opening);
+        game.actions.push(GameAction::new(
+            ActionKind::Reveal,
+            opening,
+            revealed.clone(),
+        ));
+        game.moves.extend(revealed);
+        game.started_at = Some(Utc::now());
+        game.match_id = Some(versus.id);
+        Ok((game, elapsed))
+    }
+
+    /// Called after every reveal in a match game to settle the match and notify
+    /// subscribers of the new standings.
+    pub(super) async fn update_match_progress(&self, game: &MinesweeperGame) -> AppResult<()> {
+        let Some(match_id) = game.match_id else {
+            return Ok(());
+        };
+
+        let mut versus = self.fetch_match(match_id).await?;
+        if versus.status != MatchStatus::InProgress {
+            return Ok(());
+        }
+
+        let games = self.match_games(&versus).await?;
+        if let Some(outcome) = decide_outcome(&versus, &games) {
+            let winner = match outcome {
+                MatchOutcome::Winner(user_id) => Some(user_id),
+                MatchOutcome::Draw => None,
+            };
+            // Only the first finisher's update applies; a concurrent one sees `None`
+            if let Some(finished) = self.repo.finish_match(match_id, winner, Utc::now()).await? {
+                versus = finished;
+            }
+        }
+
+        self.match_events
+            .publish(match_id, MatchDto::new(&versus, &games));
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MatchService for MinesweeperService {
+    async fn create_match(
+        &self,
+        cols: usize,
+        rows: usize,
+        mines: usize,
+        user: UserInfo,
+    ) -> AppResult<MatchDto> {
+        // The 3x3 opening around the centre has to stay clear of mines
+        if cols < 3 || rows < 3 || mines == 0 || mines > cols * rows - 9 {
+            return Err(AppError::BadRequest(
+                "Invalid board size for a match".to_string(),
+            ));
+        }
+
+        // Every attempt draws a fresh random id
+        for _ in 0..ID_ATTEMPTS {
+            let versus = VersusMatch::new(&user.sub, cols, rows, mines);
+            let id = versus.id;
+            if let Some(versus) = self.repo.insert_match(versus).await? {
+                return Ok(MatchDto::new(&versus, &[]));
+            }
+            tracing::warn!("Match id {} is already taken, picking another", id);
+        }
+
+        Err(AppError::Internal(
+            "Could not allocate a free match id".to_string(),
+        ))
+    }
+
+    async fn join_match(&self, id: MatchId, user: UserInfo) -> AppResult<MatchDto> {
+        let versus = self.fetch_match(id).await?;
+        if versus.has_player(&user.sub) {
+            return Ok(MatchDto::new(&versus, &[]));
+        }
+
+        let versus = self
+            .repo
+            .add_match_player(id, &user.sub)
+            .await?
+            .ok_or_else(|| {
+                AppError::BadRequest("Match is no longer accepting players".to_string())
+            })?;
+        self.publish_match(&versus).await
+    }
+
+    async fn start_match(&self, id: MatchId, user: UserInfo) -> AppResult<MatchDto> {
+        let versus = self.fetch_match(id).await?;
+        if versus.owner != user.sub {
+            return Err(AppError::Forbidden(
+                "Only the match owner can start it".to_string(),
+            ));
+        }
+        if versus.status != MatchStatus::Waiting {
+            return Err(AppError::BadRequest(
+                "Match has already started".to_string(),
+            ));
+        }
+        if versus.players.len() < MIN_MATCH_PLAYERS {
+            return Err(AppError::BadRequest(format!(
+                "A match needs at least {} players",
+                MIN_MATCH_PLAYERS
+            )));
+        }
+
+        let mut players = Vec::with_capacity(versus.players.len());
+        for player in &versus.players {
+            let (mut game, elapsed) = self.create_match_game(&versus).await?;
+            game.owner = Some(player.user_id.clone());
+            let game = self.insert_game(game).await?;
+            MinesweeperMetrics::record_mine_generation(&game, elapsed);
+            self.repo.add_mapping(&player.user_id, game.id).await?;
+            players.push(MatchPlayer {
+                user_id: player.user_id.clone(),
+                game_id: Some(game.id),
+            });
+        }
+
+        let versus = self
+            .repo
+            .start_match(id, &players, Utc::now())
+            .await?
+            .ok_or_else(|| AppError::BadRequest("Match has already started".to_string()))?;
+        self.publish_match(&versus).await
+    }
+
+    async fn get_match(&self, id: MatchId, user: UserInfo) -> AppResult<MatchDto> {
+        let versus = self.fetch_match(id).await?;
+        self.check_match_player(&versus, &user)?;
+        let games = self.match_games(&versus).await?;
+        Ok(MatchDto::new(&versus, &games))
+    }
+
+    async fn subscribe_match(
+        &self,
+        id: MatchId,
+        user: UserInfo,
+    ) -> AppResult<(MatchDto, broadcast::Receiver<MatchDto>)> {
+        let versus = self.fetch_match(id).await?;
+        self.check_match_player(&versus, &user)?;
+        // Subscribe before reading the state so no update can slip in between
+        let updates = self.match_events.subscribe(id);
+        let current = self.get_match(id, user).await?;
+        Ok((current, updates))
+    }
+}
@@ -1,5 +1,6 @@
 use config::{Config, ConfigError, Environment};
-use serde::Deserialize;
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
 use std::env;
 
 #[derive(Debug, Deserialize, Clone)]
@@ -20,8 +21,47 @@ pub struct ServerSettings {
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct DatabaseSettings {
+    /// Repository to use. When unset it is inferred from which connection setting is
+    /// present, for deployments that predate this setting.
+    pub backend: Option<DatabaseBackend>,
     pub addr: Option<String>,
     pub name: String,
+    pub postgres_url: Option<String>,
+    /// File used by the SQLite repository, for single-node deployments.
+    pub sqlite_path: Option<String>,
+    /// Abort startup when the repository cannot be reached instead of falling back to
+    /// the in-memory repository.
+    pub strict: bool,
+    pub connect_attempts: u32,
+    pub retry_backoff_ms: u64,
+    pub startup_timeout_secs: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Display)]
+#[serde(rename_all = "lowercase")]
+pub enum DatabaseBackend {
+    #[display("memory")]
+    Memory,
+    #[display("mongo")]
+    Mongo,
+    #[display("postgres")]
+    Postgres,
+    #[display("sqlite")]
+    Sqlite,
+}
+
+impl DatabaseSettings {
+    pub fn backend(&self) -> DatabaseBackend {
+        self.backend.unwrap_or(if self.postgres_url.is_some() {
+            DatabaseBackend::Postgres
+        } else if self.sqlite_path.is_some() {
+            DatabaseBackend::Sqlite
+        } else if self.addr.is_some() {
+            DatabaseBackend::Mongo
+        } else {
+            DatabaseBackend::Memory
+        })
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -34,6 +74,24 @@ pub struct AuthSettings {
 #[derive(Debug, Deserialize, Clone)]
 pub struct TelemetrySettings {
     pub otlp_endpoint: String,
+    pub metrics_exporter: MetricsExporter,
+}
+
+/// Where metrics go. Traces and logs are always pushed over OTLP.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Display)]
+#[serde(rename_all = "lowercase")]
+pub enum MetricsExporter {
+    /// Pushed to `otlp_endpoint`.
+    #[display("otlp")]
+    Otlp,
+    /// Served at `/metrics` for scraping.
+    #[display("prometheus")]
+    Prometheus,
+    /// Printed periodically, for local runs.
+    #[display("stdout")]
+    Stdout,
+    #[display("none")]
+    None,
 }
 
 impl Settings {
@@ -45,10 +103,15 @@ impl Settings {
             .set_default("server.allowed_origins", Vec::<String>::new())?
             .set_default("server.session_secret_key", "a".repeat(64))?
             .set_default("database.name", "MinesweeperGame")?
+            .set_default("database.strict", true)?
+            .set_default("database.connect_attempts", 5)?
+            .set_default("database.retry_backoff_ms", 500)?
+            .set_default("database.startup_timeout_secs", 30)?
             .set_default(
                 "telemetry.otlp_endpoint",
                 "http://signoz-otel-collector:4317",
-            )?;
+            )?
+            .set_default("telemetry.metrics_exporter", "otlp")?;
 
         // Manual overrides for legacy flat environment variables
         if let Ok(port) = env::var("PORT") {
@@ -70,6 +133,18 @@ impl Settings {
         if let Ok(addr) = env::var("DB_ADDR") {
             builder = builder.set_override("database.addr", addr)?;
         }
+        if let Ok(backend) = env::var("DB_BACKEND") {
+            builder = builder.set_override("database.backend", backend.to_lowercase())?;
+        }
+        if let Ok(strict) = env::var("DB_STRICT") {
+            builder = builder.set_override("database.strict", strict.to_lowercase() == "true")?;
+        }
+        if let Ok(url) = env::var("DATABASE_URL") {
+            builder = builder.set_override("database.postgres_url", url)?;
+        }
+        if let Ok(path) = env::var("SQLITE_PATH") {
+            builder = builder.set_override("database.sqlite_path", path)?;
+        }
         if let Ok(id) = env::var("GOOGLE_CLIENT_ID") {
             builder = builder.set_override("auth.google_client_id", id)?;
         }
@@ -82,6 +157,10 @@ impl Settings {
         if let Ok(endpoint) = env::var("OTEL_EXPORTER_OTLP_ENDPOINT") {
             builder = builder.set_override("telemetry.otlp_endpoint", endpoint)?;
         }
+        if let Ok(exporter) = env::var("METRICS_EXPORTER") {
+            builder =
+                builder.set_override("telemetry.metrics_exporter", exporter.to_lowercase())?;
+        }
 
         builder
             .add_source(Environment::default().separator("__"))
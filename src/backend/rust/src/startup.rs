@@ -1,6 +1,7 @@
 use crate::api;
 use crate::auth::GoogleOAuthClient;
-use crate::service::GameService;
+use crate::model::RepositoryStatus;
+use crate::service::{GameService, MatchService};
 use crate::settings::Settings;
 use actix_cors::Cors;
 pub use actix_identity::IdentityMiddleware;
@@ -18,21 +19,32 @@ pub struct Application {
 pub fn configure_app(
     cfg: &mut web::ServiceConfig,
     repo_data: web::Data<Arc<dyn crate::repository::MinesweeperRepository>>,
+    repo_status_data: web::Data<RepositoryStatus>,
     service_data: web::Data<Arc<dyn GameService>>,
+    match_service_data: web::Data<Arc<dyn MatchService>>,
     google_client: Option<web::Data<GoogleOAuthClient>>,
     settings_data: web::Data<Settings>,
 ) {
     cfg.app_data(repo_data)
+        .app_data(repo_status_data)
         .app_data(service_data)
+        .app_data(match_service_data)
         .app_data(settings_data)
+        .app_data(
+            web::PathConfig::default()
+                .error_handler(|err, _| crate::error::AppError::BadRequest(err.to_string()).into()),
+        )
         .configure(|c| {
             if let Some(ref client) = google_client {
                 c.app_data(client.clone());
             }
         })
+        .configure(api::config_health)
+        .configure(api::config_metrics)
         .configure(api::config_auth)
         .configure(api::config_game)
-        .configure(api::config_user);
+        .configure(api::config_user)
+        .configure(api::config_versus);
 }
 
 pub fn build_session_middleware(
@@ -70,7 +82,9 @@ pub fn build_cors_middleware(allowed_origins: &[String]) -> Cors {
 impl Application {
     pub async fn build(
         repo_data: web::Data<Arc<dyn crate::repository::MinesweeperRepository>>,
+        repo_status_data: web::Data<RepositoryStatus>,
         service_data: web::Data<Arc<dyn GameService>>,
+        match_service_data: web::Data<Arc<dyn MatchService>>,
         google_client: Option<web::Data<GoogleOAuthClient>>,
         settings: Settings,
         session_key: Key,
@@ -92,7 +106,9 @@ impl Application {
                     configure_app(
                         c,
                         repo_data.clone(),
+                        repo_status_data.clone(),
                         service_data.clone(),
+                        match_service_data.clone(),
                         google_client.clone(),
                         settings_data.clone(),
                     )
@@ -1,7 +1,9 @@
-use crate::model::MinesweeperGame;
-use opentelemetry::global;
-use opentelemetry::metrics::{Counter, Meter};
+use crate::model::{ActionKind, GameAction, GameStatus, MinesweeperGame};
+use crate::settings::DatabaseBackend;
+use opentelemetry::metrics::{Counter, Histogram, Meter, Unit};
+use opentelemetry::{global, KeyValue};
 use std::sync::OnceLock;
+use std::time::Duration;
 
 pub static METRICS: OnceLock<MinesweeperMetrics> = OnceLock::new();
 
@@ -10,6 +12,13 @@ pub struct MinesweeperMetrics {
     pub games_won: Counter<u64>,
     pub games_lost: Counter<u64>,
     pub moves_made: Counter<u64>,
+    pub game_duration: Histogram<u64>,
+    pub cells_revealed: Histogram<u64>,
+    pub move_duration: Histogram<f64>,
+    pub mine_generation_duration: Histogram<f64>,
+    pub reveal_size: Histogram<u64>,
+    pub dto_size: Histogram<u64>,
+    pub repository_duration: Histogram<f64>,
 }
 
 impl MinesweeperMetrics {
@@ -31,6 +40,39 @@ impl MinesweeperMetrics {
                 .u64_counter("minesweeper.moves.made")
                 .with_description("Number of moves made")
                 .init(),
+            game_duration: meter
+                .u64_histogram("minesweeper.game.duration")
+                .with_description("Time from the first click until a game was won or lost")
+                .with_unit(Unit::new("ms"))
+                .init(),
+            cells_revealed: meter
+                .u64_histogram("minesweeper.game.cells_revealed")
+                .with_description("Cells revealed by the time a game was won or lost")
+                .init(),
+            move_duration: meter
+                .f64_histogram("minesweeper.move.duration")
+                .with_description("Time taken to handle a move, including retries")
+                .with_unit(Unit::new("ms"))
+                .init(),
+            mine_generation_duration: meter
+                .f64_histogram("minesweeper.mines.generation.duration")
+                .with_description("Time taken to lay out the mines of a board")
+                .with_unit(Unit::new("ms"))
+                .init(),
+            reveal_size: meter
+                .u64_histogram("minesweeper.reveal.size")
+                .with_description("Cells opened by a single reveal, flood fill included")
+                .init(),
+            dto_size: meter
+                .u64_histogram("minesweeper.dto.size")
+                .with_description("Size of serialized game responses")
+                .with_unit(Unit::new("By"))
+                .init(),
+            repository_duration: meter
+                .f64_histogram("minesweeper.repository.duration")
+                .with_description("Time taken by each repository call")
+                .with_unit(Unit::new("ms"))
+                .init(),
         }
     }
 
@@ -44,20 +86,143 @@ impl MinesweeperMetrics {
         METRICS.get()
     }
 
-    pub fn record_game_started() {
+    pub fn record_game_started(game: &MinesweeperGame, authenticated: bool) {
         if let Some(m) = Self::get_opt() {
-            m.games_started.add(1, &[]);
+            m.games_started
+                .add(1, &game_attributes(game, authenticated));
         }
     }
 
-    pub fn record_move(game: &MinesweeperGame) {
+    /// Records a move once it has been stored, along with how many cells it opened.
+    pub fn record_move(game: &MinesweeperGame, action: &GameAction, authenticated: bool) {
         if let Some(m) = Self::get_opt() {
-            m.moves_made.add(1, &[]);
-            if game.is_game_won() {
-                m.games_won.add(1, &[]);
-            } else if game.is_game_lost() {
-                m.games_lost.add(1, &[]);
+            let action_name = action_name(action.kind);
+            let board_size = board_size_bucket(game);
+            m.moves_made.add(
+                1,
+                &[
+                    KeyValue::new("action", action_name),
+                    KeyValue::new("board_size", board_size),
+                    KeyValue::new("authenticated", authenticated),
+                ],
+            );
+            if matches!(action.kind, ActionKind::Reveal | ActionKind::Chord) {
+                m.reveal_size.record(
+                    action.revealed.len() as u64,
+                    &[
+                        KeyValue::new("action", action_name),
+                        KeyValue::new("board_size", board_size),
+                    ],
+                );
             }
         }
     }
+
+    /// Records a game reaching its final state; called once, when it is marked finished.
+    pub fn record_game_finished(game: &MinesweeperGame, authenticated: bool) {
+        let Some(m) = Self::get_opt() else {
+            return;
+        };
+        let outcome = match game.status() {
+            GameStatus::Won => &m.games_won,
+            GameStatus::Lost => &m.games_lost,
+            GameStatus::InProgress => return,
+        };
+
+        let attributes = game_attributes(game, authenticated);
+        outcome.add(1, &attributes);
+        m.game_duration
+            .record(game.elapsed_ms() as u64, &attributes);
+        m.cells_revealed
+            .record(game.moves.len() as u64, &attributes);
+    }
+
+    pub fn record_move_duration(action: &'static str, game: &MinesweeperGame, elapsed: Duration) {
+        if let Some(m) = Self::get_opt() {
+            m.move_duration.record(
+                millis(elapsed),
+                &[
+                    KeyValue::new("action", action),
+                    KeyValue::new("board_size", board_size_bucket(game)),
+                ],
+            );
+        }
+    }
+
+    pub fn record_mine_generation(game: &MinesweeperGame, elapsed: Duration) {
+        if let Some(m) = Self::get_opt() {
+            m.mine_generation_duration.record(
+                millis(elapsed),
+                &[
+                    KeyValue::new("board_size", board_size_bucket(game)),
+                    KeyValue::new("no_guess", game.no_guess),
+                ],
+            );
+        }
+    }
+
+    pub fn record_dto_size(dto: &'static str, game: &MinesweeperGame, bytes: usize) {
+        if let Some(m) = Self::get_opt() {
+            m.dto_size.record(
+                bytes as u64,
+                &[
+                    KeyValue::new("dto", dto),
+                    KeyValue::new("board_size", board_size_bucket(game)),
+                ],
+            );
+        }
+    }
+
+    pub fn record_repository_call(
+        backend: DatabaseBackend,
+        operation: &'static str,
+        succeeded: bool,
+        elapsed: Duration,
+    ) {
+        if let Some(m) = Self::get_opt() {
+            m.repository_duration.record(
+                millis(elapsed),
+                &[
+                    KeyValue::new("backend", backend.to_string()),
+                    KeyValue::new("operation", operation),
+                    KeyValue::new("outcome", if succeeded { "ok" } else { "error" }),
+                ],
+            );
+        }
+    }
+}
+
+/// Groups boards by cell count around the classic difficulties (9x9, 16x16 and 30x16),
+/// keeping label cardinality fixed however large custom boards get.
+pub fn board_size_bucket(game: &MinesweeperGame) -> &'static str {
+    match game.cols * game.rows {
+        0..=81 => "small",
+        82..=256 => "medium",
+        257..=480 => "large",
+        _ => "huge",
+    }
+}
+
+/// Exact dimensions and mine counts are left to the "Game finished" log event, as any
+/// board size the API accepts would otherwise become its own series.
+fn game_attributes(game: &MinesweeperGame, authenticated: bool) -> [KeyValue; 4] {
+    [
+        KeyValue::new("board_size", board_size_bucket(game)),
+        KeyValue::new("no_guess", game.no_guess),
+        KeyValue::new("practice", game.practice),
+        KeyValue::new("authenticated", authenticated),
+    ]
+}
+
+fn action_name(kind: ActionKind) -> &'static str {
+    match kind {
+        ActionKind::Reveal => "reveal",
+        ActionKind::Chord => "chord",
+        ActionKind::Flag => "flag",
+        ActionKind::Unflag => "unflag",
+    }
+}
+
+fn millis(elapsed: Duration) -> f64 {
+    elapsed.as_secs_f64() * 1000.0
 }
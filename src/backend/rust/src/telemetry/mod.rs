@@ -1,13 +1,23 @@
 pub mod metrics;
+pub mod prometheus;
+pub mod stdout;
 
 use opentelemetry::{global, KeyValue};
 use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::metrics::reader::{DefaultAggregationSelector, DefaultTemporalitySelector};
+use opentelemetry_sdk::metrics::{MeterProvider, PeriodicReader};
 use opentelemetry_sdk::{runtime, trace as sdktrace, Resource};
 use tracing_subscriber::prelude::*;
 
-use crate::settings::Settings;
+use self::prometheus::PrometheusExporter;
+use self::stdout::StdoutExporter;
+use crate::settings::{MetricsExporter, Settings};
+
+/// Sets up tracing, logging and metrics export. A pipeline that cannot be installed is
+/// left out with a warning rather than stopping the server from starting.
 pub fn init_telemetry(settings: &Settings) {
     let otlp_endpoint = &settings.telemetry.otlp_endpoint;
+    let mut failures = Vec::new();
 
     let resource = Resource::new(vec![
         KeyValue::new("service.name", "rust-backend"),
@@ -24,26 +34,24 @@ pub fn init_telemetry(settings: &Settings) {
         )
         .with_trace_config(sdktrace::config().with_resource(resource.clone()))
         .install_batch(runtime::Tokio)
-        .expect("Failed to install tracer");
+        .map_err(|e| failures.push(format!("tracing: {}", e)))
+        .ok();
 
-    global::set_tracer_provider(tracer.provider().unwrap());
+    if let Some(provider) = tracer.as_ref().and_then(|t| t.provider()) {
+        global::set_tracer_provider(provider);
+    }
 
     // Configure Metrics
-    let meter_provider = opentelemetry_otlp::new_pipeline()
-        .metrics(runtime::Tokio)
-        .with_exporter(
-            opentelemetry_otlp::new_exporter()
-                .tonic()
-                .with_endpoint(otlp_endpoint.clone()),
-        )
-        .with_resource(resource.clone())
-        .build()
-        .expect("Failed to build metrics pipeline");
-
-    global::set_meter_provider(meter_provider);
+    match build_meter_provider(settings, resource.clone()) {
+        Ok(Some(meter_provider)) => {
+            global::set_meter_provider(meter_provider);
+        }
+        Ok(None) => {}
+        Err(e) => failures.push(format!("metrics: {}", e)),
+    }
 
     // Configure Logs
-    let logger = opentelemetry_otlp::new_pipeline()
+    let logger_provider = opentelemetry_otlp::new_pipeline()
         .logging()
         .with_log_config(opentelemetry_sdk::logs::Config::default().with_resource(resource))
         .with_exporter(
@@ -52,21 +60,21 @@ pub fn init_telemetry(settings: &Settings) {
                 .with_endpoint(otlp_endpoint.clone()),
         )
         .install_batch(runtime::Tokio)
-        .expect("Failed to install logger");
-
-    let logger_provider = logger
-        .provider()
-        .expect("Failed to get logger provider")
-        .clone();
+        .map_err(|e| failures.push(format!("logs: {}", e)))
+        .ok()
+        .and_then(|logger| logger.provider());
 
     // Set the global logger provider as well
-    global::set_logger_provider(logger_provider.clone());
+    if let Some(ref provider) = logger_provider {
+        global::set_logger_provider(provider.clone());
+    }
 
-    let otel_log_layer =
-        opentelemetry_appender_tracing::layer::OpenTelemetryTracingBridge::new(&logger_provider);
+    let otel_log_layer = logger_provider
+        .as_ref()
+        .map(opentelemetry_appender_tracing::layer::OpenTelemetryTracingBridge::new);
 
     // Initialize Tracing Subscriber
-    let telemetry = tracing_opentelemetry::layer().with_tracer(tracer);
+    let telemetry = tracer.map(|tracer| tracing_opentelemetry::layer().with_tracer(tracer));
     let filter = tracing_subscriber::EnvFilter::from_default_env()
         .add_directive("info".parse().unwrap())
         .add_directive("rust_backend=info".parse().unwrap()); // Ensure our own logs are included
@@ -84,5 +92,43 @@ pub fn init_telemetry(settings: &Settings) {
     // Initialize custom metrics from the internal module
     self::metrics::MinesweeperMetrics::init();
 
-    tracing::info!("Telemetry initialized successfully");
+    if failures.is_empty() {
+        tracing::info!("Telemetry initialized successfully");
+    }
+    for failure in failures {
+        tracing::warn!("Telemetry export disabled for {}", failure);
+    }
+}
+
+/// Builds the meter provider for the configured exporter, or `None` when metrics are
+/// not exported at all.
+fn build_meter_provider(
+    settings: &Settings,
+    resource: Resource,
+) -> opentelemetry::metrics::Result<Option<MeterProvider>> {
+    let builder = MeterProvider::builder().with_resource(resource);
+    let builder = match settings.telemetry.metrics_exporter {
+        MetricsExporter::Otlp => {
+            let exporter = opentelemetry_otlp::MetricsExporterBuilder::from(
+                opentelemetry_otlp::new_exporter()
+                    .tonic()
+                    .with_endpoint(settings.telemetry.otlp_endpoint.clone()),
+            )
+            .build_metrics_exporter(
+                Box::new(DefaultTemporalitySelector::new()),
+                Box::new(DefaultAggregationSelector::new()),
+            )?;
+            builder.with_reader(PeriodicReader::builder(exporter, runtime::Tokio).build())
+        }
+        MetricsExporter::Prometheus => {
+            let exporter = PrometheusExporter::default();
+            exporter.register();
+            builder.with_reader(exporter)
+        }
+        MetricsExporter::Stdout => builder.with_reader(
+            PeriodicReader::builder(StdoutExporter::default(), runtime::Tokio).build(),
+        ),
+        MetricsExporter::None => return Ok(None),
+    };
+    Ok(Some(builder.build()))
 }
@@ -0,0 +1,240 @@
+use opentelemetry::metrics::Result;
+use opentelemetry::Value;
+use opentelemetry_sdk::metrics::data::{
+    Gauge, Histogram, Metric, ResourceMetrics, Sum, Temporality,
+};
+use opentelemetry_sdk::metrics::reader::{AggregationSelector, MetricReader, TemporalitySelector};
+use opentelemetry_sdk::metrics::{Aggregation, InstrumentKind, ManualReader, Pipeline};
+use opentelemetry_sdk::AttributeSet;
+use opentelemetry_sdk::Resource;
+use std::fmt::{Display, Write};
+use std::sync::{Arc, OnceLock, Weak};
+
+static EXPORTER: OnceLock<PrometheusExporter> = OnceLock::new();
+
+/// Pull-based reader whose metrics are rendered in the Prometheus text format on scrape.
+#[derive(Debug, Clone)]
+pub struct PrometheusExporter {
+    reader: Arc<ManualReader>,
+}
+
+impl Default for PrometheusExporter {
+    fn default() -> Self {
+        PrometheusExporter {
+            reader: Arc::new(ManualReader::builder().build()),
+        }
+    }
+}
+
+impl PrometheusExporter {
+    /// Makes this the exporter served at `/metrics`. Only the first one registered is kept.
+    pub fn register(&self) -> bool {
+        EXPORTER.set(self.clone()).is_ok()
+    }
+
+    pub fn registered() -> Option<&'static PrometheusExporter> {
+        EXPORTER.get()
+    }
+
+    pub fn render(&self) -> Result<String> {
+        let mut metrics = ResourceMetrics {
+            resource: Resource::empty(),
+            scope_metrics: Vec::new(),
+        };
+        self.reader.collect(&mut metrics)?;
+        Ok(encode(&metrics))
+    }
+}
+
+impl TemporalitySelector for PrometheusExporter {
+    fn temporality(&self, kind: InstrumentKind) -> Temporality {
+        self.reader.temporality(kind)
+    }
+}
+
+impl AggregationSelector for PrometheusExporter {
+    fn aggregation(&self, kind: InstrumentKind) -> Aggregation {
+        self.reader.aggregation(kind)
+    }
+}
+
+impl MetricReader for PrometheusExporter {
+    fn register_pipeline(&self, pipeline: Weak<Pipeline>) {
+        self.reader.register_pipeline(pipeline)
+    }
+
+    fn collect(&self, rm: &mut ResourceMetrics) -> Result<()> {
+        self.reader.collect(rm)
+    }
+
+    fn force_flush(&self) -> Result<()> {
+        self.reader.force_flush()
+    }
+
+    fn shutdown(&self) -> Result<()> {
+        self.reader.shutdown()
+    }
+}
+
+/// Renders collected metrics in the Prometheus text exposition format. Monotonic sums
+/// become counters, everything else but histograms a gauge.
+pub(super) fn encode(metrics: &ResourceMetrics) -> String {
+    let mut out = String::new();
+    for metric in metrics.scope_metrics.iter().flat_map(|s| &s.metrics) {
+        let data = metric.data.as_any();
+        if let Some(sum) = data.downcast_ref::<Sum<u64>>() {
+            encode_sum(&mut out, metric, sum);
+        } else if let Some(sum) = data.downcast_ref::<Sum<i64>>() {
+            encode_sum(&mut out, metric, sum);
+        } else if let Some(sum) = data.downcast_ref::<Sum<f64>>() {
+            encode_sum(&mut out, metric, sum);
+        } else if let Some(gauge) = data.downcast_ref::<Gauge<u64>>() {
+            encode_gauge(&mut out, metric, gauge);
+        } else if let Some(gauge) = data.downcast_ref::<Gauge<i64>>() {
+            encode_gauge(&mut out, metric, gauge);
+        } else if let Some(gauge) = data.downcast_ref::<Gauge<f64>>() {
+            encode_gauge(&mut out, metric, gauge);
+        } else if let Some(histogram) = data.downcast_ref::<Histogram<u64>>() {
+            encode_histogram(&mut out, metric, histogram);
+        } else if let Some(histogram) = data.downcast_ref::<Histogram<i64>>() {
+            encode_histogram(&mut out, metric, histogram);
+        } else if let Some(histogram) = data.downcast_ref::<Histogram<f64>>() {
+            encode_histogram(&mut out, metric, histogram);
+        }
+    }
+    out
+}
+
+fn encode_sum<T: Display>(out: &mut String, metric: &Metric, sum: &Sum<T>) {
+    let (name, kind) = if sum.is_monotonic {
+        (format!("{}_total", metric_name(&metric.name)), "counter")
+    } else {
+        (metric_name(&metric.name), "gauge")
+    };
+    write_header(out, &name, &metric.description, kind);
+    for point in &sum.data_points {
+        let _ = writeln!(
+            out,
+            "{}{} {}",
+            name,
+            labels(&point.attributes, None),
+            point.value
+        );
+    }
+}
+
+fn encode_gauge<T: Display>(out: &mut String, metric: &Metric, gauge: &Gauge<T>) {
+    let name = metric_name(&metric.name);
+    write_header(out, &name, &metric.description, "gauge");
+    for point in &gauge.data_points {
+        let _ = writeln!(
+            out,
+            "{}{} {}",
+            name,
+            labels(&point.attributes, None),
+            point.value
+        );
+    }
+}
+
+fn encode_histogram<T: Display>(out: &mut String, metric: &Metric, histogram: &Histogram<T>) {
+    let name = metric_name(&metric.name);
+    write_header(out, &name, &metric.description, "histogram");
+    for point in &histogram.data_points {
+        let mut cumulative = 0;
+        for (bound, count) in point.bounds.iter().zip(&point.bucket_counts) {
+            cumulative += count;
+            let le = labels(&point.attributes, Some(&bound.to_string()));
+            let _ = writeln!(out, "{}_bucket{} {}", name, le, cumulative);
+        }
+        let le = labels(&point.attributes, Some("+Inf"));
+        let _ = writeln!(out, "{}_bucket{} {}", name, le, point.count);
+        let plain = labels(&point.attributes, None);
+        let _ = writeln!(out, "{}_sum{} {}", name, plain, point.sum);
+        let _ = writeln!(out, "{}_count{} {}", name, plain, point.count);
+    }
+}
+
+fn write_header(out: &mut String, name: &str, description: &str, kind: &str) {
+    if !description.is_empty() {
+        let _ = writeln!(out, "# HELP {} {}", name, description.replace('\n', " "));
+    }
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// OpenTelemetry names use dots, which Prometheus does not allow.
+fn metric_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == ':' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn labels(attributes: &AttributeSet, le: Option<&str>) -> String {
+    let mut pairs: Vec<String> = attributes
+        .iter()
+        .map(|(key, value)| {
+            let value = match value {
+                Value::String(s) => s.as_str().to_string(),
+                other => other.to_string(),
+            };
+            format!("{}=\"{}\"", metric_name(key.as_str()), escape(&value))
+        })
+        .collect();
+    if let Some(le) = le {
+        pairs.push(format!("le=\"{}\"", le));
+    }
+
+    if pairs.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", pairs.join(","))
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::metrics::MeterProvider as _;
+    use opentelemetry::KeyValue;
+    use opentelemetry_sdk::metrics::MeterProvider;
+
+    #[test]
+    fn renders_counters_and_histograms() {
+        let exporter = PrometheusExporter::default();
+        let provider = MeterProvider::builder()
+            .with_reader(exporter.clone())
+            .build();
+        let meter = provider.meter("test");
+
+        let counter = meter
+            .u64_counter("games.started")
+            .with_description("Games started")
+            .init();
+        counter.add(2, &[KeyValue::new("mode", "no\"guess")]);
+        let histogram = meter.f64_histogram("move.latency").init();
+        histogram.record(3.0, &[]);
+
+        let text = exporter.render().unwrap();
+        assert!(text.contains("# HELP games_started_total Games started\n"));
+        assert!(text.contains("# TYPE games_started_total counter\n"));
+        assert!(text.contains("games_started_total{mode=\"no\\\"guess\"} 2\n"));
+        assert!(text.contains("# TYPE move_latency histogram\n"));
+        assert!(text.contains("move_latency_bucket{le=\"5\"} 1\n"));
+        assert!(text.contains("move_latency_bucket{le=\"+Inf\"} 1\n"));
+        assert!(text.contains("move_latency_sum 3\n"));
+        assert!(text.contains("move_latency_count 1\n"));
+    }
+}
@@ -0,0 +1,48 @@
+use super::prometheus::encode;
+use async_trait::async_trait;
+use opentelemetry::metrics::Result;
+use opentelemetry_sdk::metrics::data::{ResourceMetrics, Temporality};
+use opentelemetry_sdk::metrics::exporter::PushMetricsExporter;
+use opentelemetry_sdk::metrics::reader::{
+    AggregationSelector, DefaultAggregationSelector, DefaultTemporalitySelector,
+    TemporalitySelector,
+};
+use opentelemetry_sdk::metrics::{Aggregation, InstrumentKind};
+use std::io::Write;
+
+/// Prints every collection to stdout in the Prometheus text format, for local runs
+/// without a collector.
+#[derive(Debug, Default)]
+pub struct StdoutExporter {
+    temporality: DefaultTemporalitySelector,
+    aggregation: DefaultAggregationSelector,
+}
+
+impl TemporalitySelector for StdoutExporter {
+    fn temporality(&self, kind: InstrumentKind) -> Temporality {
+        self.temporality.temporality(kind)
+    }
+}
+
+impl AggregationSelector for StdoutExporter {
+    fn aggregation(&self, kind: InstrumentKind) -> Aggregation {
+        self.aggregation.aggregation(kind)
+    }
+}
+
+#[async_trait]
+impl PushMetricsExporter for StdoutExporter {
+    async fn export(&self, metrics: &mut ResourceMetrics) -> Result<()> {
+        let mut stdout = std::io::stdout().lock();
+        let _ = stdout.write_all(encode(metrics).as_bytes());
+        Ok(())
+    }
+
+    async fn force_flush(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn shutdown(&self) -> Result<()> {
+        Ok(())
+    }
+}
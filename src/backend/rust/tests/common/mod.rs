@@ -1,23 +1,55 @@
 use actix_web::dev::{Service, ServiceRequest, ServiceResponse};
-use actix_web::{cookie::Key, test, web, App, HttpMessage};
+use actix_web::{cookie::Key, test, web, App, HttpMessage, HttpServer};
 use once_cell::sync::Lazy;
 use rust_backend::api;
+use rust_backend::model::{GameId, MatchId};
 use rust_backend::repository::MinesweeperRepository;
-use rust_backend::service::{GameService, MinesweeperService};
+use rust_backend::service::{GameService, MatchService, MinesweeperService};
 use rust_backend::startup::{build_session_middleware, configure_app, IdentityMiddleware};
 use rust_backend::telemetry::metrics::MinesweeperMetrics;
+use rust_backend::telemetry::prometheus::PrometheusExporter;
+use std::collections::HashMap;
 use std::sync::Arc;
 
+/// Game metrics are process-wide, so every test app records into one exporter that
+/// `/metrics` serves.
 static INIT: Lazy<()> = Lazy::new(|| {
+    let exporter = PrometheusExporter::default();
+    exporter.register();
+    opentelemetry::global::set_meter_provider(
+        opentelemetry_sdk::metrics::MeterProvider::builder()
+            .with_reader(exporter)
+            .build(),
+    );
     MinesweeperMetrics::init();
 });
 
 pub const X_MOCK_AUTH: &str = "X-Mock-Auth";
 
+pub fn uri_health() -> String {
+    api::SCOPE_HEALTH.to_string()
+}
+
+pub fn uri_health_live() -> String {
+    format!("{}{}", api::SCOPE_HEALTH, api::PATH_LIVE)
+}
+
+pub fn uri_health_ready() -> String {
+    format!("{}{}", api::SCOPE_HEALTH, api::PATH_READY)
+}
+
+pub fn uri_metrics() -> String {
+    api::PATH_METRICS.to_string()
+}
+
 pub fn uri_user_games() -> String {
     format!("{}{}", api::SCOPE_USER, api::PATH_GAMES)
 }
 
+pub fn uri_user_stats() -> String {
+    format!("{}{}", api::SCOPE_USER, api::PATH_STATS)
+}
+
 pub fn uri_new_game(cols: usize, rows: usize, mines: usize) -> String {
     format!("{}{}", api::SCOPE_GAME, api::PATH_NEW_CUSTOM)
         .replace("{cols}", &cols.to_string())
@@ -25,14 +57,158 @@ pub fn uri_new_game(cols: usize, rows: usize, mines: usize) -> String {
         .replace("{mines}", &mines.to_string())
 }
 
-pub fn uri_game(id: i32) -> String {
+pub fn uri_new_seeded_game(cols: usize, rows: usize, mines: usize, seed: u32) -> String {
+    format!("{}?seed={}", uri_new_game(cols, rows, mines), seed)
+}
+
+pub fn uri_game(id: GameId) -> String {
     format!("{}{}", api::SCOPE_GAME, api::PATH_ID).replace("{id}", &id.to_string())
 }
 
-pub fn uri_flag(id: i32) -> String {
+pub fn uri_game_diff(id: GameId) -> String {
+    format!("{}?diff=true", uri_game(id))
+}
+
+pub fn uri_chord(id: GameId) -> String {
+    format!("{}{}", api::SCOPE_GAME, api::PATH_CHORD_ID).replace("{id}", &id.to_string())
+}
+
+pub fn uri_hint(id: GameId) -> String {
+    format!("{}{}", api::SCOPE_GAME, api::PATH_HINT).replace("{id}", &id.to_string())
+}
+
+pub fn uri_probabilities(id: GameId) -> String {
+    format!("{}{}", api::SCOPE_GAME, api::PATH_PROBABILITIES).replace("{id}", &id.to_string())
+}
+
+pub fn uri_replay(id: GameId, step: usize) -> String {
+    format!("{}{}?step={}", api::SCOPE_GAME, api::PATH_REPLAY, step)
+        .replace("{id}", &id.to_string())
+}
+
+pub fn uri_undo(id: GameId) -> String {
+    format!("{}{}", api::SCOPE_GAME, api::PATH_UNDO).replace("{id}", &id.to_string())
+}
+
+pub fn uri_invite(id: GameId) -> String {
+    format!("{}{}", api::SCOPE_GAME, api::PATH_INVITE).replace("{id}", &id.to_string())
+}
+
+pub fn uri_invite_code(id: GameId) -> String {
+    format!("{}{}", api::SCOPE_GAME, api::PATH_INVITE_CODE).replace("{id}", &id.to_string())
+}
+
+pub fn uri_join(code: &str) -> String {
+    format!("{}{}", api::SCOPE_GAME, api::PATH_JOIN).replace("{code}", code)
+}
+
+pub fn uri_ws(id: GameId) -> String {
+    format!("{}{}", api::SCOPE_GAME, api::PATH_WS).replace("{id}", &id.to_string())
+}
+
+pub fn uri_events(id: GameId) -> String {
+    format!("{}{}", api::SCOPE_GAME, api::PATH_EVENTS).replace("{id}", &id.to_string())
+}
+
+pub fn uri_visibility(id: GameId) -> String {
+    format!("{}{}", api::SCOPE_GAME, api::PATH_VISIBILITY).replace("{id}", &id.to_string())
+}
+
+pub fn uri_flag(id: GameId) -> String {
     format!("{}{}", api::SCOPE_GAME, api::PATH_FLAG_ID).replace("{id}", &id.to_string())
 }
 
+pub fn uri_new_match(cols: usize, rows: usize, mines: usize) -> String {
+    format!("{}{}", api::SCOPE_MATCH, api::PATH_MATCH_NEW)
+        .replace("{cols}", &cols.to_string())
+        .replace("{rows}", &rows.to_string())
+        .replace("{mines}", &mines.to_string())
+}
+
+pub fn uri_match(id: MatchId) -> String {
+    format!("{}{}", api::SCOPE_MATCH, api::PATH_MATCH_ID).replace("{id}", &id.to_string())
+}
+
+pub fn uri_match_join(id: MatchId) -> String {
+    format!("{}{}", api::SCOPE_MATCH, api::PATH_MATCH_JOIN).replace("{id}", &id.to_string())
+}
+
+pub fn uri_match_start(id: MatchId) -> String {
+    format!("{}{}", api::SCOPE_MATCH, api::PATH_MATCH_START).replace("{id}", &id.to_string())
+}
+
+pub fn uri_match_events(id: MatchId) -> String {
+    format!("{}{}", api::SCOPE_MATCH, api::PATH_MATCH_EVENTS).replace("{id}", &id.to_string())
+}
+
+/// Reads the next server-sent event from a streaming response body and decodes its payload.
+pub async fn next_sse_event<B, T>(body: &mut B) -> T
+where
+    B: actix_web::body::MessageBody + Unpin,
+    B::Error: std::fmt::Debug,
+    T: serde::de::DeserializeOwned,
+{
+    let chunk = std::future::poll_fn(|cx| std::pin::Pin::new(&mut *body).poll_next(cx))
+        .await
+        .expect("Event stream ended")
+        .expect("Event stream failed");
+    let text = std::str::from_utf8(&chunk).unwrap();
+    let data = text
+        .strip_prefix("data: ")
+        .expect("Malformed event")
+        .trim_end();
+    serde_json::from_str(data).unwrap()
+}
+
+pub type WsClient =
+    tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+pub async fn connect_ws(
+    addr: std::net::SocketAddr,
+    id: GameId,
+    user_sub: &str,
+) -> Result<WsClient, tokio_tungstenite::tungstenite::Error> {
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+    let mut request = format!("ws://{}{}", addr, uri_ws(id))
+        .into_client_request()
+        .unwrap();
+    request
+        .headers_mut()
+        .insert(X_MOCK_AUTH, "true".parse().unwrap());
+    request
+        .headers_mut()
+        .insert("X-User-Sub", user_sub.parse().unwrap());
+    tokio_tungstenite::connect_async(request)
+        .await
+        .map(|(ws, _)| ws)
+}
+
+pub async fn next_ws_message<T: serde::de::DeserializeOwned>(ws: &mut WsClient) -> T {
+    use futures_util::StreamExt;
+    use tokio_tungstenite::tungstenite::Message;
+
+    loop {
+        let message = tokio::time::timeout(std::time::Duration::from_secs(5), ws.next())
+            .await
+            .expect("Timed out waiting for a WebSocket message")
+            .expect("WebSocket closed")
+            .expect("WebSocket error");
+        if let Message::Text(text) = message {
+            return serde_json::from_str(&text).unwrap();
+        }
+    }
+}
+
+pub async fn send_ws_message<T: serde::Serialize>(ws: &mut WsClient, message: &T) {
+    use futures_util::SinkExt;
+    use tokio_tungstenite::tungstenite::Message;
+
+    ws.send(Message::Text(serde_json::to_string(message).unwrap()))
+        .await
+        .unwrap();
+}
+
 /// Middleware that injects a mock identity if the X-Mock-Auth header is present.
 pub fn mock_auth_middleware<S, B>(
     req: ServiceRequest,
@@ -58,17 +234,11 @@ where
 }
 
 use rust_backend::engine::MinesweeperEngine;
-use rust_backend::settings::Settings;
+use rust_backend::model::RepositoryStatus;
+use rust_backend::settings::{DatabaseBackend, Settings};
 
-pub async fn create_test_app(
-    repo: Arc<dyn MinesweeperRepository>,
-) -> impl actix_web::dev::Service<
-    actix_http::Request,
-    Response = actix_web::dev::ServiceResponse,
-    Error = actix_web::Error,
-> {
-    Lazy::force(&INIT);
-    let settings = Settings::new().unwrap_or_else(|_| {
+fn test_settings() -> Settings {
+    Settings::new().unwrap_or_else(|_| {
         // Fallback for tests if env vars aren't set
         Settings {
             server: rust_backend::settings::ServerSettings {
@@ -78,8 +248,15 @@ pub async fn create_test_app(
                 session_secret_key: "a".repeat(64),
             },
             database: rust_backend::settings::DatabaseSettings {
+                backend: None,
                 addr: None,
                 name: "TestDB".to_string(),
+                postgres_url: None,
+                sqlite_path: None,
+                strict: true,
+                connect_attempts: 1,
+                retry_backoff_ms: 0,
+                startup_timeout_secs: 5,
             },
             auth: rust_backend::settings::AuthSettings {
                 google_client_id: "id".to_string(),
@@ -88,15 +265,69 @@ pub async fn create_test_app(
             },
             telemetry: rust_backend::settings::TelemetrySettings {
                 otlp_endpoint: "http://localhost:4317".to_string(),
+                metrics_exporter: rust_backend::settings::MetricsExporter::None,
             },
         }
-    });
+    })
+}
+
+#[derive(Clone)]
+struct AppData {
+    repo: web::Data<Arc<dyn MinesweeperRepository>>,
+    repo_status: web::Data<RepositoryStatus>,
+    game_service: web::Data<Arc<dyn GameService>>,
+    match_service: web::Data<Arc<dyn MatchService>>,
+    settings: web::Data<Settings>,
+}
+
+impl AppData {
+    fn new(repo: Arc<dyn MinesweeperRepository>, repo_status: RepositoryStatus) -> Self {
+        Lazy::force(&INIT);
+        let engine = Arc::new(MinesweeperEngine);
+        let service = Arc::new(MinesweeperService::new(repo.clone(), engine));
+        let game_service: Arc<dyn GameService> = service.clone();
+        let match_service: Arc<dyn MatchService> = service;
+        AppData {
+            repo: web::Data::new(repo),
+            repo_status: web::Data::new(repo_status),
+            game_service: web::Data::new(game_service),
+            match_service: web::Data::new(match_service),
+            settings: web::Data::new(test_settings()),
+        }
+    }
+
+    fn configure(self, cfg: &mut web::ServiceConfig) {
+        configure_app(
+            cfg,
+            self.repo,
+            self.repo_status,
+            self.game_service,
+            self.match_service,
+            None,
+            self.settings,
+        )
+    }
+}
 
-    let repo_data = web::Data::new(repo.clone());
-    let engine = Arc::new(MinesweeperEngine);
-    let service: Arc<dyn GameService> = Arc::new(MinesweeperService::new(repo, engine));
-    let service_data = web::Data::new(service);
-    let settings_data = web::Data::new(settings.clone());
+pub async fn create_test_app(
+    repo: Arc<dyn MinesweeperRepository>,
+) -> impl actix_web::dev::Service<
+    actix_http::Request,
+    Response = actix_web::dev::ServiceResponse,
+    Error = actix_web::Error,
+> {
+    create_test_app_with_status(repo, RepositoryStatus::healthy(DatabaseBackend::Memory)).await
+}
+
+pub async fn create_test_app_with_status(
+    repo: Arc<dyn MinesweeperRepository>,
+    repo_status: RepositoryStatus,
+) -> impl actix_web::dev::Service<
+    actix_http::Request,
+    Response = actix_web::dev::ServiceResponse,
+    Error = actix_web::Error,
+> {
+    let data = AppData::new(repo, repo_status);
     let secret_key = Key::generate();
 
     test::init_service(
@@ -104,11 +335,34 @@ pub async fn create_test_app(
             .wrap_fn(mock_auth_middleware)
             .wrap(IdentityMiddleware::default())
             .wrap(build_session_middleware(secret_key.clone(), false))
-            .configure(|c| configure_app(c, repo_data, service_data, None, settings_data)),
+            .configure(|c| data.configure(c)),
     )
     .await
 }
 
+/// Serves the app on a local port for tests that need a real connection, such as
+/// WebSocket upgrades which the in-process test service cannot perform.
+pub fn spawn_test_server(repo: Arc<dyn MinesweeperRepository>) -> std::net::SocketAddr {
+    let data = AppData::new(repo, RepositoryStatus::healthy(DatabaseBackend::Memory));
+    let secret_key = Key::generate();
+
+    let server = HttpServer::new(move || {
+        let data = data.clone();
+        App::new()
+            .wrap_fn(mock_auth_middleware)
+            .wrap(IdentityMiddleware::default())
+            .wrap(build_session_middleware(secret_key.clone(), false))
+            .configure(|c| data.configure(c))
+    })
+    .workers(1)
+    .bind(("127.0.0.1", 0))
+    .expect("Failed to bind test server");
+
+    let addr = server.addrs()[0];
+    actix_web::rt::spawn(server.run());
+    addr
+}
+
 #[derive(Default)]
 pub struct MongoImage;
 
@@ -133,3 +387,58 @@ impl testcontainers::Image for MongoImage {
         vec![27017]
     }
 }
+
+pub struct PostgresImage {
+    env_vars: HashMap<String, String>,
+}
+
+impl Default for PostgresImage {
+    fn default() -> Self {
+        let env_vars = HashMap::from([("POSTGRES_PASSWORD".to_string(), "postgres".to_string())]);
+        PostgresImage { env_vars }
+    }
+}
+
+impl testcontainers::Image for PostgresImage {
+    type Args = Vec<String>;
+
+    fn name(&self) -> String {
+        "postgres".to_string()
+    }
+
+    fn tag(&self) -> String {
+        "15-alpine".to_string()
+    }
+
+    fn ready_conditions(&self) -> Vec<testcontainers::core::WaitFor> {
+        // The entrypoint restarts the server once after initialising the data directory
+        vec![
+            testcontainers::core::WaitFor::message_on_stderr(
+                "database system is ready to accept connections",
+            ),
+            testcontainers::core::WaitFor::message_on_stderr(
+                "database system is ready to accept connections",
+            ),
+        ]
+    }
+
+    fn env_vars(&self) -> Box<dyn Iterator<Item = (&String, &String)> + '_> {
+        Box::new(self.env_vars.iter())
+    }
+
+    fn expose_ports(&self) -> Vec<u16> {
+        vec![5432]
+    }
+}
+
+/// Creates an empty database on the server at `url` so each test gets its own schema.
+pub async fn create_postgres_database(url: &str, name: &str) {
+    use sqlx::Connection;
+    let mut conn = sqlx::PgConnection::connect(&format!("{}/postgres", url))
+        .await
+        .expect("Failed to connect to Postgres");
+    sqlx::query(&format!("CREATE DATABASE \"{}\"", name))
+        .execute(&mut conn)
+        .await
+        .expect("Failed to create test database");
+}